@@ -1,16 +1,33 @@
 use console::style;
 use dialoguer::Input;
-use hypatia_lib::{eval, format_unit, parse, report_error, units::Quantity, Environment, Error, Value};
+use hypatia_lib::{
+    ends_with_operator, eval, number::NumberStyle, parse, report_error, Environment, Error, Value,
+};
 
-fn run(source: &str, env: &mut Environment) -> Result<String, Vec<Error>> {
+fn run(source: &str, env: &mut Environment) -> Result<Option<String>, Vec<Error>> {
     let ast = parse(source)?;
     let value = eval(&ast, env).map_err(|error| vec![error])?;
     Ok(match value {
+        // Declarations of units and prefixes evaluate to nothing, no need to print that
+        Value::Nothing => None,
         Value::Quantity(quantity) => {
-            let (Quantity{number, unit: _}, (long_name, _)) = format_unit(quantity, env);
-            format!("{number} {long_name}")
+            let quantity = quantity.normalize_named(env);
+            let number = quantity.number.format(NumberStyle::ExactWithApprox);
+            let unit = match Some(&quantity.unit)
+                .filter(|unit| !unit.is_unitless())
+                .and_then(|unit| env.unit_name(unit))
+            {
+                Some((long_name, short_name)) => short_name.unwrap_or(long_name),
+                // A combination of units without a name of its own, like m^2 s
+                None => quantity.unit.to_string(),
+            };
+            Some(if unit.is_empty() {
+                number
+            } else {
+                format!("{number} {unit}")
+            })
         }
-        other => format!("{other}"),
+        other => Some(format!("{other}")),
     })
 }
 
@@ -26,25 +43,62 @@ fn get_input() -> Option<String> {
         open_blocks += line.matches('{').count();
         open_blocks -= line.matches('}').count();
 
-        if open_blocks == 0 {
+        // A line ending with a binary operator continues on the next line
+        if open_blocks == 0 && !ends_with_operator(&line) {
             break;
         }
     }
     Some(result)
 }
 
+/// Create the environment that the REPL starts with, also used to reset it with ":clear".
+fn new_environment(no_prelude: bool) -> Environment {
+    if no_prelude {
+        Environment::without_prelude()
+    } else {
+        Environment::default()
+    }
+}
+
 fn main() {
-    let mut env = Environment::default();
+    // Start without any predefined units, prefixes or functions when
+    // passed "--no-prelude", useful for trying out unit systems from scratch
+    let no_prelude = std::env::args().skip(1).any(|arg| arg == "--no-prelude");
+    let mut env = new_environment(no_prelude);
     loop {
         if let Some(input) = get_input() {
+            // Forget all variables, functions and units declared so far
+            if input.trim() == ":clear" {
+                env = new_environment(no_prelude);
+                println!("{}", style("Cleared the environment").dim());
+                continue;
+            }
+
             match run(&input, &mut env) {
                 Err(errors) => {
                     for error in errors {
                         println!("{}", style(report_error(error, &input)).red());
                     }
                 }
-                Ok(result) => println!("{}", style(result).green()),
+                Ok(Some(result)) => println!("{}", style(result).green()),
+                Ok(None) => {}
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn run_formats_quantities() {
+        let mut env = Environment::default();
+        let mut output = |source: &str| run(source, &mut env).unwrap().unwrap();
+
+        assert_eq!(output("1/3"), "1/3 (≈ 0.3333333333333333)");
+        assert_eq!(output("2 km"), "2000 m");
+        // A unit without a name is written in its base units
+        assert_eq!(output("2 m^3 / 3 s"), "2/3 (≈ 0.6666666666666666) m^3/s");
+    }
+}
@@ -1,18 +1,50 @@
 use ariadne::{Color, Fmt, Label, Report, ReportKind, Source};
 use std::io::Cursor;
-use syntax::{Simple, SimpleReason};
+use syntax::{expr::Span, Simple, SimpleReason};
 
 #[derive(Debug, Clone)]
 pub enum Error {
     Parsing(Simple<String>),
     ErrorNode,
     UnknownName(String),
+    UnknownUnit(String, Span),
     UpdateNonExistentVar(String),
     InvalidType,
+    /// The name of the function (if any), the expected kind and the kind that was found instead
+    UnexpectedType(Option<String>, &'static str, &'static str),
+    /// The kinds of values produced by the two branches of an `if`
+    TypeMismatch(&'static str, &'static str),
     InvalidUnitOperation,
     OccupiedName(String),
     Redeclaration(String),
     ForbiddenName(String),
+    /// The span of the condition of an `assert` which was false
+    AssertionFailed(Span),
+    /// The name of the function which failed to find a solution and the reason why
+    NoSolution(String, &'static str),
+    /// The name of the function and the largest number of values it is allowed to create
+    TooManyValues(String, usize),
+    /// The name of the function, the expected kind of argument and the argument that was given
+    InvalidArgument(String, &'static str, String),
+    /// The name of the function (if any), how many arguments it takes and how many it was given
+    WrongArgumentCount(Option<String>, String, usize),
+    ZeroToNegativePower,
+    /// The span of the part of a unit that is not a unit name, product, quotient or power
+    NotAUnit(Span),
+}
+
+impl Error {
+    /// The location of the error in the source text, if it is known. Only parsing errors
+    /// and some of the errors from evaluating the program carry a span so far.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Error::Parsing(error) => Some(error.span()),
+            Error::UnknownUnit(_, span) | Error::AssertionFailed(span) | Error::NotAUnit(span) => {
+                Some(span.clone())
+            }
+            _ => None,
+        }
+    }
 }
 
 pub fn report_error(error: Error, src: &str) -> String {
@@ -90,6 +122,56 @@ pub fn report_error(error: Error, src: &str) -> String {
 
             String::from_utf8(result.into_inner()).unwrap()
         }
+        Error::UnknownUnit(name, span) => {
+            let mut result = Cursor::new(Vec::new());
+            Report::build(ReportKind::Error, (), span.start)
+                .with_message(format!("Unknown unit {}", name.as_str().fg(Color::Red)))
+                .with_label(
+                    Label::new(span)
+                        .with_message(format!(
+                            "There is no unit or prefixed unit named {}",
+                            name.as_str().fg(Color::Red)
+                        ))
+                        .with_color(Color::Red),
+                )
+                .finish()
+                .write(Source::from(src), &mut result)
+                .unwrap();
+
+            String::from_utf8(result.into_inner()).unwrap()
+        }
+        Error::AssertionFailed(span) => {
+            let mut result = Cursor::new(Vec::new());
+            let condition = src.get(span.clone()).unwrap_or_default();
+            Report::build(ReportKind::Error, (), span.start)
+                .with_message(format!("Assertion failed: {}", condition.fg(Color::Red)))
+                .with_label(
+                    Label::new(span)
+                        .with_message("This condition is false")
+                        .with_color(Color::Red),
+                )
+                .finish()
+                .write(Source::from(src), &mut result)
+                .unwrap();
+
+            String::from_utf8(result.into_inner()).unwrap()
+        }
+        Error::NotAUnit(span) => {
+            let mut result = Cursor::new(Vec::new());
+            let expression = src.get(span.clone()).unwrap_or_default();
+            Report::build(ReportKind::Error, (), span.start)
+                .with_message(format!("Not a unit: {}", expression.fg(Color::Red)))
+                .with_label(
+                    Label::new(span)
+                        .with_message("Only unit names, products, quotients and powers are allowed")
+                        .with_color(Color::Red),
+                )
+                .finish()
+                .write(Source::from(src), &mut result)
+                .unwrap();
+
+            String::from_utf8(result.into_inner()).unwrap()
+        }
         // FIXME: add spans to these, then we can create nicer
         //  error reports for these as well
         Error::ErrorNode => String::from("Error node"),
@@ -98,6 +180,17 @@ pub fn report_error(error: Error, src: &str) -> String {
             format!("You cannot update the variable {name} because it has not been declared yet.")
         }
         Error::InvalidType => "Invalid type.".to_string(),
+        Error::UnexpectedType(Some(function), expected, found) => {
+            format!("{function} expected {expected} but got a value of type {found}.")
+        }
+        Error::UnexpectedType(None, expected, found) => {
+            format!("Expected {expected} but got a value of type {found}.")
+        }
+        Error::TypeMismatch(a, b) => {
+            format!(
+                "The branches of the if expression give different types of values, {a} and {b}."
+            )
+        }
         Error::InvalidUnitOperation => "Invalid unit operation.".to_string(),
         Error::OccupiedName(name) => format!("Occupied name {name}."),
         Error::Redeclaration(name) => {
@@ -106,5 +199,21 @@ pub fn report_error(error: Error, src: &str) -> String {
             )
         }
         Error::ForbiddenName(name) => format!("'{name}' is not a valid variable name"),
+        Error::NoSolution(function, reason) => {
+            format!("{function} could not find a solution, {reason}.")
+        }
+        Error::TooManyValues(function, max) => {
+            format!("{function} would create more than {max} values.")
+        }
+        Error::InvalidArgument(function, expected, found) => {
+            format!("{function} expected {expected} but got {found}.")
+        }
+        Error::WrongArgumentCount(Some(function), expected, found) => {
+            format!("{function} takes {expected} but was given {found}.")
+        }
+        Error::WrongArgumentCount(None, expected, found) => {
+            format!("The function takes {expected} but was given {found}.")
+        }
+        Error::ZeroToNegativePower => "Zero cannot be raised to a negative power.".to_string(),
     }
 }
@@ -1,7 +1,8 @@
-use num::rational::Ratio;
+use num::{rational::Ratio, BigRational};
 use std::sync::{Arc, Mutex};
 
 use crate::{
+    natives,
     number::Number,
     parse,
     trie::StringTrie,
@@ -9,16 +10,20 @@ use crate::{
     Error, Expr,
 };
 use std::cmp;
-use std::collections::{BTreeMap, HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
-use syntax::expr::{BinOp, Literal, NumberLiteral, Spanned, UnaryOp};
+use std::ops::RangeInclusive;
+use syntax::expr::{BinOp, Literal, NumberLiteral, Parameters, Spanned, UnaryOp};
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum Value {
     Nothing,
     Bool(bool),
+    String(String),
     Quantity(Quantity),
     Function(Function),
+    NativeFunction(NativeFunction),
+    List(Vec<Value>),
 }
 
 impl Value {
@@ -26,8 +31,11 @@ impl Value {
         match self {
             Value::Nothing => Ok(false),
             Value::Bool(b) => Ok(*b),
+            Value::String(_) => Err(Error::InvalidType),
             Value::Quantity(_) => Err(Error::InvalidType),
             Value::Function(_) => Err(Error::InvalidType),
+            Value::NativeFunction(_) => Err(Error::InvalidType),
+            Value::List(_) => Err(Error::InvalidType),
         }
     }
 
@@ -35,22 +43,133 @@ impl Value {
         Ok(!self.is_true()?)
     }
 
-    pub fn quantity(&self) -> Result<Quantity, Error> {
-        if let Value::Quantity(q) = self {
-            Ok(q.clone())
-        } else {
-            Err(Error::InvalidType)
+    pub fn is_nothing(&self) -> bool {
+        matches!(self, Value::Nothing)
+    }
+
+    pub fn is_bool(&self) -> bool {
+        matches!(self, Value::Bool(_))
+    }
+
+    pub fn is_quantity(&self) -> bool {
+        matches!(self, Value::Quantity(_))
+    }
+
+    /// True for both functions declared in Hypatia and native functions
+    pub fn is_function(&self) -> bool {
+        matches!(self, Value::Function(_) | Value::NativeFunction(_))
+    }
+
+    /// The name of the kind of value, used in error messages
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Value::Nothing => "nothing",
+            Value::Bool(_) => "bool",
+            Value::String(_) => "string",
+            Value::Quantity(_) => "quantity",
+            Value::Function(_) | Value::NativeFunction(_) => "function",
+            Value::List(_) => "list",
         }
     }
 
-    pub fn boolean(&self) -> Result<bool, Error> {
-        if let Value::Bool(b) = self {
-            Ok(*b)
-        } else {
-            Err(Error::InvalidType)
+    /// Create an error saying that this value is not of the expected kind,
+    /// `expected` should include an article, for example "a quantity"
+    pub fn unexpected(&self, fn_name: &str, expected: &'static str) -> Error {
+        Error::UnexpectedType(Some(fn_name.to_string()), expected, self.kind())
+    }
+
+    /// Create an error saying that this value is of the right kind but not a valid argument,
+    /// like a quantity with a unit where a plain number is needed. `expected` should include
+    /// an article, for example "an integer"
+    pub fn invalid(&self, fn_name: &str, expected: &'static str) -> Error {
+        Error::InvalidArgument(fn_name.to_string(), expected, self.to_string())
+    }
+
+    fn as_quantity(&self, fn_name: Option<&str>) -> Result<Quantity, Error> {
+        match self {
+            Value::Quantity(q) => Ok(q.clone()),
+            _ => Err(Error::UnexpectedType(
+                fn_name.map(String::from),
+                "a quantity",
+                self.kind(),
+            )),
+        }
+    }
+
+    fn as_bool(&self, fn_name: Option<&str>) -> Result<bool, Error> {
+        match self {
+            Value::Bool(b) => Ok(*b),
+            _ => Err(Error::UnexpectedType(
+                fn_name.map(String::from),
+                "a bool",
+                self.kind(),
+            )),
         }
     }
 
+    /// Get the quantity, or an error naming the function that expected it
+    pub fn expect_quantity(&self, fn_name: &str) -> Result<Quantity, Error> {
+        self.as_quantity(Some(fn_name))
+    }
+
+    /// Get the number of a quantity, or an error naming the function that expected it
+    pub fn expect_number(&self, fn_name: &str) -> Result<Number, Error> {
+        Ok(self.expect_quantity(fn_name)?.number)
+    }
+
+    /// Get the bool, or an error naming the function that expected it
+    pub fn expect_bool(&self, fn_name: &str) -> Result<bool, Error> {
+        self.as_bool(Some(fn_name))
+    }
+
+    /// Get a function (declared in Hypatia or native) as a Rust closure which can be called with
+    /// argument values, for example to pass a user defined function to an algorithm on the Rust side.
+    /// The closure evaluates the body of the function in the environment it was declared in.
+    pub fn as_callable(&self) -> Result<impl Fn(&[Value]) -> Result<Value, Error>, Error> {
+        self.as_function(None)
+    }
+
+    /// Like [`Value::as_callable`], but with an error naming the function that expected it
+    pub fn expect_callable(
+        &self,
+        fn_name: &str,
+    ) -> Result<impl Fn(&[Value]) -> Result<Value, Error>, Error> {
+        self.as_function(Some(fn_name))
+    }
+
+    fn as_function(
+        &self,
+        fn_name: Option<&str>,
+    ) -> Result<impl Fn(&[Value]) -> Result<Value, Error>, Error> {
+        if !self.is_function() {
+            return Err(Error::UnexpectedType(
+                fn_name.map(String::from),
+                "a function",
+                self.kind(),
+            ));
+        }
+
+        let function = self.clone();
+        Ok(move |arguments: &[Value]| function.call(arguments))
+    }
+
+    /// Call a function or native function with some argument values
+    fn call(&self, arguments: &[Value]) -> Result<Value, Error> {
+        match self {
+            Value::Function(function) => function.call(arguments),
+            Value::NativeFunction(function) => function.call(arguments),
+            _ => Err(Error::UnexpectedType(None, "a function", self.kind())),
+        }
+    }
+
+    pub fn quantity(&self) -> Result<Quantity, Error> {
+        self.as_quantity(None)
+    }
+
+    pub fn boolean(&self) -> Result<bool, Error> {
+        self.as_bool(None)
+    }
+
     pub fn number(&self) -> Result<Number, Error> {
         Ok(self.quantity()?.number)
     }
@@ -61,12 +180,22 @@ impl fmt::Display for Value {
         match self {
             Value::Nothing => write!(f, "nothing"),
             Value::Bool(b) => write!(f, "{}", if *b { "true" } else { "false" }),
+            Value::String(s) => write!(f, "{s}"),
             Value::Quantity(q) => {
                 // FIXME: We should not always normalize when displaying, still need to implement a way of
                 //  showing the result in the most suitable unit
                 write!(f, "{}", q.clone().normalize())
             }
             Value::Function(_) => write!(f, "Function"),
+            Value::NativeFunction(function) => write!(f, "Native function {}", function.name),
+            Value::List(values) => {
+                let values = values
+                    .iter()
+                    .map(|value| value.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                write!(f, "[{values}]")
+            }
         }
     }
 }
@@ -74,28 +203,111 @@ impl fmt::Display for Value {
 #[derive(Clone, Debug)]
 pub struct Function {
     body: Spanned<Expr>,
-    parameters: Vec<String>,
+    parameters: Parameters,
     env: Environment, // FIXME: I need to make the Environment a lot cheaper to clone, should just be a smart pointer.
                       // That means that I need to move the units and prefixes into Arc<Mutex<..>>
 }
 
+impl Function {
+    fn call(&self, arguments: &[Value]) -> Result<Value, Error> {
+        let Parameters { names, rest } = &self.parameters;
+
+        // A function with a rest parameter accepts any number of extra arguments
+        if arguments.len() < names.len() || (rest.is_none() && arguments.len() != names.len()) {
+            let expected = match rest {
+                Some(_) => format!("at least {}", count_arguments(names.len())),
+                None => count_arguments(names.len()),
+            };
+            return Err(Error::WrongArgumentCount(None, expected, arguments.len()));
+        }
+        let (values, extra_values) = arguments.split_at(names.len());
+
+        // Create a new scope and add all the arguments
+        // (note: important to use the environment from the actual closure here)
+        let mut env = self.env.clone();
+        env.push_scope();
+        for (name, value) in names.iter().zip(values) {
+            env.declare_var(name, value)?;
+        }
+
+        // Any remaining arguments are collected into a list
+        if let Some(name) = rest {
+            env.declare_var(name, &Value::List(extra_values.to_vec()))?;
+        }
+
+        // Finally, evaluate the function body
+        eval(&self.body, &mut env)
+    }
+}
+
 impl cmp::PartialEq for Function {
     fn eq(&self, _: &Self) -> bool {
         false
     }
 }
 
+type NativeFn = dyn Fn(&[Value]) -> Result<Value, Error> + Send + Sync;
+
+/// A function implemented in Rust rather than in Hypatia itself
+#[derive(Clone)]
+pub struct NativeFunction {
+    name: String,
+    arity: RangeInclusive<usize>,
+    function: Arc<NativeFn>,
+}
+
+impl NativeFunction {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn call(&self, arguments: &[Value]) -> Result<Value, Error> {
+        if !self.arity.contains(&arguments.len()) {
+            let (min, max) = (*self.arity.start(), *self.arity.end());
+            let expected = if min == max {
+                count_arguments(min)
+            } else {
+                format!("{min} to {}", count_arguments(max))
+            };
+            return Err(Error::WrongArgumentCount(
+                Some(self.name.clone()),
+                expected,
+                arguments.len(),
+            ));
+        }
+        (self.function)(arguments)
+    }
+}
+
+impl fmt::Debug for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NativeFunction")
+            .field("name", &self.name)
+            .field("arity", &self.arity)
+            .finish()
+    }
+}
+
+impl cmp::PartialEq for NativeFunction {
+    fn eq(&self, _: &Self) -> bool {
+        false
+    }
+}
+
 /// Used to keep track of additional information related to a Unit/Prefix
-/// such as if it is a long or short name
+/// such as if it is a long or short name and if it was declared by the prelude
 #[derive(Debug, Clone, PartialEq)]
 struct Entry<T> {
     is_long_name: bool,
+    is_builtin: bool,
     value: T,
 }
 
 #[derive(Debug, Clone)]
 struct VariableScope {
     table: HashMap<String, Value>,
+    // Set for the scope containing the variables declared by the prelude
+    is_builtin: bool,
     // Note: Will need to be thread safe since the Environment
     // is stored in a global variable in implementation the front-end
     outer: Option<Arc<Mutex<Self>>>,
@@ -105,10 +317,34 @@ impl VariableScope {
     fn new() -> Self {
         Self {
             table: HashMap::new(),
+            is_builtin: false,
             outer: None,
         }
     }
 
+    /// A copy of the scope and all of its outer scopes that shares nothing with the original
+    fn deep_clone(&self) -> Self {
+        Self {
+            table: self.table.clone(),
+            is_builtin: self.is_builtin,
+            outer: self
+                .outer
+                .as_ref()
+                .map(|outer| Arc::new(Mutex::new(outer.lock().unwrap().deep_clone()))),
+        }
+    }
+
+    /// Check if the scope where the variable is found is the prelude scope
+    fn is_builtin_var(&self, name: &str) -> Option<bool> {
+        if self.table.contains_key(name) {
+            Some(self.is_builtin)
+        } else {
+            self.outer
+                .as_ref()
+                .and_then(|outer| outer.lock().unwrap().is_builtin_var(name))
+        }
+    }
+
     fn get_var(&self, name: &str) -> Option<Value> {
         self.table.get(name).cloned().or_else(|| {
             self.outer
@@ -125,6 +361,15 @@ impl VariableScope {
         Ok(())
     }
 
+    /// The names of all variables that are visible from this scope
+    fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.table.keys().cloned().collect();
+        if let Some(outer) = self.outer.as_ref() {
+            names.extend(outer.lock().unwrap().names());
+        }
+        names
+    }
+
     fn update_var(&mut self, name: &str, value: Value) -> Result<(), Error> {
         if self.table.contains_key(name) {
             self.table.insert(name.to_string(), value);
@@ -137,13 +382,59 @@ impl VariableScope {
     }
 }
 
+/// The base units and their exponents which together make up the dimension of a unit
+type BaseUnits = BTreeMap<BaseUnit, Ratio<i32>>;
+
+/// The long and optional short name of a unit
+type UnitName = (String, Option<String>);
+
+/// How to choose between several named units with the same dimension and scale when
+/// displaying a quantity, like "meter" and its alias "metre". See [`Environment::set_tie_break`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum TieBreak {
+    /// The unit that was declared first, so aliases never take over from the original unit
+    #[default]
+    FirstDeclared,
+    /// The unit that was declared most recently
+    LastDeclared,
+    /// The first declared unit that has a short name
+    ShortName,
+    /// The first unit in the list of long names, useful to always prefer some unit system
+    /// over another. Units that are not in the list are only used if none of them match.
+    Ranking(Vec<String>),
+}
+
+impl TieBreak {
+    /// Pick one of the candidates, which are given in declaration order
+    fn choose(&self, candidates: Vec<UnitName>) -> Option<UnitName> {
+        let chosen = match self {
+            TieBreak::FirstDeclared => None,
+            TieBreak::LastDeclared => candidates.last(),
+            TieBreak::ShortName => candidates
+                .iter()
+                .find(|(_, short_name)| short_name.is_some()),
+            TieBreak::Ranking(names) => names
+                .iter()
+                .find_map(|name| candidates.iter().find(|(long_name, _)| long_name == name)),
+        };
+        chosen.or(candidates.first()).cloned()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Environment {
     variables: Arc<Mutex<VariableScope>>,
     units: Arc<Mutex<HashMap<String, Entry<Unit>>>>,
-    unit_names:
-        Arc<Mutex<HashMap<BTreeMap<BaseUnit, Ratio<i32>>, HashSet<(String, Option<String>)>>>>,
+    // The names are kept in declaration order, so an alias like "unit metre = m"
+    // does not take over how quantities in the original unit are displayed
+    unit_names: Arc<Mutex<HashMap<BaseUnits, Vec<UnitName>>>>,
+    preferred_units: Arc<Mutex<HashMap<BaseUnits, UnitName>>>,
     prefixes: Arc<Mutex<StringTrie<Entry<Number>>>>,
+    // Units and prefixes declared while this is set are marked as built-in
+    loading_prelude: bool,
+    // See `Environment::set_strict`
+    strict: bool,
+    tie_break: TieBreak,
 }
 
 impl Environment {
@@ -156,17 +447,130 @@ impl Environment {
             variables: Arc::new(Mutex::new(VariableScope::new())),
             units: Arc::new(Mutex::new(HashMap::new())),
             unit_names: Arc::new(Mutex::new(HashMap::new())),
+            preferred_units: Arc::new(Mutex::new(HashMap::new())),
             prefixes: Arc::new(Mutex::new(StringTrie::new())),
+            loading_prelude: false,
+            strict: false,
+            tie_break: TieBreak::default(),
         }
     }
 
+    /// A copy of the environment that can be changed without affecting the original. A plain
+    /// clone shares its variables, units and prefixes with the original, so anything declared
+    /// or updated through the clone is visible through the original as well.
+    pub fn snapshot(&self) -> Self {
+        Self {
+            variables: Arc::new(Mutex::new(self.variables.lock().unwrap().deep_clone())),
+            units: Arc::new(Mutex::new(self.units.lock().unwrap().clone())),
+            unit_names: Arc::new(Mutex::new(self.unit_names.lock().unwrap().clone())),
+            preferred_units: Arc::new(Mutex::new(self.preferred_units.lock().unwrap().clone())),
+            prefixes: Arc::new(Mutex::new(self.prefixes.lock().unwrap().clone())),
+            ..self.clone()
+        }
+    }
+
+    /// In strict mode it is an error for the branches of an `if` to evaluate to different kinds
+    /// of values, like `if x {1 m} else {true}`. Without any type inference this can only be
+    /// checked for branches where the kind is clear from the syntax alone, so branches that
+    /// depend on a variable or a function call are always accepted. Nothing is also accepted in
+    /// either branch, since an `if` without an `else` gives nothing when the condition is false.
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    /// Choose how to pick between named units with the same dimension and scale when
+    /// displaying quantities. A unit declared as preferred always wins over the others.
+    pub fn set_tie_break(&mut self, tie_break: TieBreak) {
+        self.tie_break = tie_break;
+    }
+
     fn add_prelude(mut self) -> Self {
-        let prelude_src = include_str!("prelude.hyp");
-        let prelude_ast = parse(prelude_src).expect("Failed to parse prelude");
+        self.loading_prelude = true;
+        self.variables.lock().unwrap().is_builtin = true;
+
+        natives::declare_natives(&mut self);
+
+        let prelude_ast = parse(Self::prelude_source()).expect("Failed to parse prelude");
         eval(&prelude_ast, &mut self).expect("Failed to evaluate prelude");
+
+        // Give the user a scope of their own so that it is possible
+        // to declare variables with the same name as a native function
+        self.push_scope();
+        self.loading_prelude = false;
         self
     }
 
+    /// The source text of the prelude which declares the predefined units and prefixes
+    pub fn prelude_source() -> &'static str {
+        include_str!("prelude.hyp")
+    }
+
+    /// Check if a unit, prefix or variable with this name was declared by the prelude.
+    /// Names that the user has redeclared are not considered to be built-in.
+    pub fn is_builtin(&self, name: &str) -> bool {
+        if let Some(unit) = self.units.lock().unwrap().get(name) {
+            return unit.is_builtin;
+        }
+
+        if let Some(is_builtin) = self.variables.lock().unwrap().is_builtin_var(name) {
+            return is_builtin;
+        }
+
+        self.prefixes
+            .lock()
+            .unwrap()
+            .search(name)
+            .into_iter()
+            .any(|(prefix_name, prefix)| prefix_name == name && prefix.is_builtin)
+    }
+
+    /// The names of all units, prefixes and variables declared by the prelude
+    pub fn builtin_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.units.lock().unwrap().keys().cloned().collect();
+        names.extend(self.prefixes.lock().unwrap().keys());
+        names.extend(self.variables.lock().unwrap().names());
+
+        names.retain(|name| self.is_builtin(name));
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    pub(crate) fn declare_native(
+        &mut self,
+        name: &str,
+        arity: RangeInclusive<usize>,
+        function: impl Fn(&[Value]) -> Result<Value, Error> + Send + Sync + 'static,
+    ) {
+        self.try_declare_native(name, arity, function)
+            .expect("Failed to declare native function");
+    }
+
+    /// Make a function implemented in Rust callable from Hypatia code, for example to look up
+    /// currency rates in the host application. It is always called with `arity` arguments.
+    pub fn register_native(
+        &mut self,
+        name: &str,
+        arity: usize,
+        function: impl Fn(&[Value]) -> Result<Value, Error> + Send + Sync + 'static,
+    ) -> Result<(), Error> {
+        self.try_declare_native(name, arity..=arity, function)
+    }
+
+    fn try_declare_native(
+        &mut self,
+        name: &str,
+        arity: RangeInclusive<usize>,
+        function: impl Fn(&[Value]) -> Result<Value, Error> + Send + Sync + 'static,
+    ) -> Result<(), Error> {
+        let function = Value::NativeFunction(NativeFunction {
+            name: name.to_string(),
+            arity,
+            function: Arc::new(function),
+        });
+        self.declare_var(name, &function)
+    }
+
     fn get_var(&self, name: &str) -> Result<Value, Error> {
         if name == "_" {
             return Err(Error::ForbiddenName(name.into()));
@@ -225,6 +629,7 @@ impl Environment {
         long_name: &str,
         short_name: &Option<String>,
         derivation: Option<&Value>,
+        is_preferred: bool,
     ) -> Result<(), Error> {
         let derived_unit;
         // handle derived units
@@ -249,6 +654,7 @@ impl Environment {
             long_name.to_string(),
             Entry {
                 is_long_name: true,
+                is_builtin: self.loading_prelude,
                 value: derived_unit.clone(),
             },
         );
@@ -259,6 +665,7 @@ impl Environment {
                 name.clone(),
                 Entry {
                     is_long_name: false,
+                    is_builtin: self.loading_prelude,
                     value: derived_unit.clone(),
                 },
             );
@@ -269,10 +676,18 @@ impl Environment {
         // For example, [kg^1, m^1, s^-2] -> ("Newton", "N").
         let mut unit_names = self.unit_names.lock().unwrap();
         let entry = (long_name.to_string(), short_name.clone());
-        if let Some(names) = unit_names.get_mut(&derived_unit.1) {
-            names.insert(entry);
-        } else {
-            unit_names.insert(derived_unit.1, [entry].into());
+
+        // A preferred unit will be used to display all quantities of the same dimension
+        if is_preferred {
+            self.preferred_units
+                .lock()
+                .unwrap()
+                .insert(derived_unit.1.clone(), entry.clone());
+        }
+
+        let names = unit_names.entry(derived_unit.1).or_default();
+        if !names.contains(&entry) {
+            names.push(entry);
         }
 
         Ok(())
@@ -309,21 +724,102 @@ impl Environment {
         Err(Error::UnknownName(name.to_string()))
     }
 
-    fn get_unit_names(
-        &self,
-        base_units: &BTreeMap<BaseUnit, Ratio<i32>>,
-    ) -> HashSet<(String, Option<String>)> {
+    fn get_unit_names(&self, base_units: &BaseUnits) -> Vec<UnitName> {
         let unit_names = self.unit_names.lock().unwrap();
-        unit_names
+        unit_names.get(base_units).cloned().unwrap_or_default()
+    }
+
+    /// Parse a unit on its own, like "km/h" or "kg m/s^2", for example the target unit of a
+    /// conversion typed into a separate field. Only unit names, products, quotients and powers
+    /// are allowed, and any numeric factor is ignored so "1000 m" is simply a meter.
+    pub fn parse_unit(&self, source: &str) -> Result<Unit, Error> {
+        let program = parse(source).map_err(|mut errors| errors.remove(0))?;
+        let expr = match &program {
+            (Expr::Program(statements), _) if statements.len() == 1 => &statements[0],
+            (_, span) => return Err(Error::NotAUnit(span.clone())),
+        };
+
+        // The check makes sure that nothing is declared or updated, so a plain clone is enough
+        self.check_unit_expr(expr)?;
+        Ok(eval(expr, &mut self.clone())?.quantity()?.unit)
+    }
+
+    /// Make sure that an expression only consists of units, so that evaluating it has no side effects
+    fn check_unit_expr(&self, (expr, span): &Spanned<Expr>) -> Result<(), Error> {
+        match expr {
+            Expr::Variable(name) => match self.get_unit(name) {
+                Ok(_) => Ok(()),
+                Err(_) => Err(Error::UnknownUnit(name.clone(), span.clone())),
+            },
+            Expr::Literal(Literal::Quantity(..) | Literal::Rational(..)) => Ok(()),
+            Expr::BinOp(BinOp::Mul | BinOp::Div | BinOp::Pow, a, b) => {
+                self.check_unit_expr(a)?;
+                self.check_unit_expr(b)
+            }
+            _ => Err(Error::NotAUnit(span.clone())),
+        }
+    }
+
+    /// Find the long and short name of a declared unit with exactly the same
+    /// scale and base units as the given unit.
+    pub fn unit_name(&self, unit: &Unit) -> Option<UnitName> {
+        let Unit(scale, base_units) = unit;
+
+        // Compare the scale of this unit with the scale used in our Quantity,
+        // is there a named unit with the same scale?
+        let has_same_scale = |(long_name, _): &UnitName| {
+            let Ok(Unit(other_scale, _)) = self.get_unit(long_name) else {
+                return false;
+            };
+
+            Number::abs(other_scale - scale.clone()) == Number::zero()
+        };
+
+        // The preferred unit wins over any other unit with the same scale
+        self.preferred_unit(base_units)
+            .filter(has_same_scale)
+            .or_else(|| {
+                let candidates = self
+                    .get_unit_names(base_units)
+                    .into_iter()
+                    .filter(has_same_scale)
+                    .collect();
+                self.tie_break.choose(candidates)
+            })
+    }
+
+    /// All declared units with the same dimension as the quantity (so all lengths for
+    /// `5 meter`) regardless of their scale, sorted from the smallest to the largest unit.
+    pub fn commensurable_units(&self, quantity: &Quantity) -> Vec<(UnitName, Unit)> {
+        let base_units = &quantity.unit.1;
+        let mut units: Vec<_> = self
+            .get_unit_names(base_units)
+            .into_iter()
+            .filter_map(|name| {
+                // The name might since have been redeclared as a unit of another dimension
+                let unit = self.get_unit(&name.0).ok()?;
+                (&unit.1 == base_units).then_some((name, unit))
+            })
+            .collect();
+
+        units.sort_by(|(_, Unit(a, _)), (_, Unit(b, _))| (a.clone() - b.clone()).signum().cmp(&0));
+        units
+    }
+
+    /// The unit which has been declared as the preferred way to display a dimension
+    fn preferred_unit(&self, base_units: &BaseUnits) -> Option<UnitName> {
+        self.preferred_units
+            .lock()
+            .unwrap()
             .get(base_units)
             .cloned()
-            .unwrap_or_else(|| HashSet::new())
     }
 
     fn push_scope(&mut self) {
         let outer_scope = Arc::clone(&self.variables);
         let new_scope = VariableScope {
             outer: Some(outer_scope),
+            is_builtin: false,
             table: HashMap::new(),
         };
 
@@ -338,6 +834,17 @@ impl Environment {
         self.variables = outer_scope;
     }
 
+    /// Find a prefix with the value 10^exp, that goes together with a long or short unit name
+    fn prefix_for_power_of_ten(&self, exp: i32, is_long_name: bool) -> Option<String> {
+        let value = Number::new(10).powi(exp);
+        self.prefixes
+            .lock()
+            .unwrap()
+            .entries()
+            .find(|(_, prefix)| prefix.is_long_name == is_long_name && prefix.value == value)
+            .map(|(name, _)| name)
+    }
+
     fn declare_prefix(
         &mut self,
         name: &str,
@@ -353,6 +860,7 @@ impl Environment {
                 name,
                 Entry {
                     is_long_name,
+                    is_builtin: self.loading_prelude,
                     value,
                 },
             );
@@ -371,6 +879,11 @@ impl Default for Environment {
 pub fn eval((expr, _): &Spanned<Expr>, env: &mut Environment) -> Result<Value, Error> {
     match &expr {
         Expr::Error => Err(Error::ErrorNode),
+        Expr::Assert(condition) => match eval(condition, env)? {
+            Value::Bool(true) => Ok(Value::Nothing),
+            Value::Bool(false) => Err(Error::AssertionFailed(condition.1.clone())),
+            _ => Err(Error::InvalidType),
+        },
         Expr::Literal(literal) => eval_literal(literal, env),
         Expr::Variable(name) => env.get_var(name),
         Expr::VarDeclaration(name, rhs) => {
@@ -384,26 +897,17 @@ pub fn eval((expr, _): &Spanned<Expr>, env: &mut Environment) -> Result<Value, E
             Ok(value)
         }
         Expr::Call(callable, arguments) => {
-            let Value::Function(mut function) = eval(callable, env)? else {
-               return Err(Error::InvalidType);
-            };
-
-            if function.parameters.len() != arguments.len() {
-                return Err(Error::InvalidType);
+            let function = eval(callable, env)?;
+            if !function.is_function() {
+                return Err(Error::UnexpectedType(None, "a function", function.kind()));
             }
 
-            // Create a new scope and add all the arguments
-            function.env.push_scope();
             // Evaluate  the arguments (note: use the env at the call site)
-            let values: Vec<Result<_, _>> = arguments.iter().map(|arg| eval(arg, env)).collect();
-
-            for (name, value) in function.parameters.iter().zip(values.into_iter()) {
-                env.declare_var(name, &value?)?;
-            }
-
-            // Finally, evaluate the function body
-            // (note: important to use the environment from the actual closure here)
-            eval(&function.body, &mut function.env)
+            let values = arguments
+                .iter()
+                .map(|arg| eval(arg, env))
+                .collect::<Result<Vec<_>, _>>()?;
+            function.call(&values)
         }
 
         Expr::FunctionDecl(name, parameters, body) => {
@@ -429,6 +933,14 @@ pub fn eval((expr, _): &Spanned<Expr>, env: &mut Environment) -> Result<Value, E
             Ok(function)
         }
         Expr::If(cond, a, b) => {
+            if env.strict {
+                if let (Some(a), Some(b)) = (syntactic_kind(&a.0), syntactic_kind(&b.0)) {
+                    if a != b {
+                        return Err(Error::TypeMismatch(a, b));
+                    }
+                }
+            }
+
             let cond = eval(cond, env)?;
             if cond.is_true()? {
                 eval(a, env)
@@ -460,26 +972,40 @@ pub fn eval((expr, _): &Spanned<Expr>, env: &mut Environment) -> Result<Value, E
                 And => Value::Bool(eval(a, env)?.is_true()? && eval(b, env)?.is_true()?),
                 Or => Value::Bool(eval(a, env)?.is_true()? || eval(b, env)?.is_true()?),
                 Xor => Value::Bool(eval(a, env)?.is_true()? ^ eval(b, env)?.is_true()?),
+                Pow => Value::Quantity(eval(a, env)?.quantity()?.pow(eval(b, env)?.quantity()?)?),
             })
         }
-        Expr::BaseUnitDecl(long_name, short_name) => {
-            env.declare_unit(long_name, short_name, None)?;
+        Expr::BaseUnitDecl(long_name, short_name, is_preferred) => {
+            env.declare_unit(long_name, short_name, None, *is_preferred)?;
             Ok(Value::Nothing)
         }
-        Expr::DerivedUnitDecl(long_name, short_name, expr) => {
+        Expr::DerivedUnitDecl(long_name, short_name, expr, is_preferred) => {
             // FIXME: Maybe disallow "normal" variables to be used in the rhs
             let value = eval(expr, env)?;
-            env.declare_unit(long_name, short_name, Some(&value))?;
+            env.declare_unit(long_name, short_name, Some(&value), *is_preferred)?;
             Ok(Value::Nothing)
         }
         Expr::PrefixDecl(long_name, short_name, rhs) => {
             let value = eval(rhs, env)?.number()?; // FIXME: ensure that it is dimensionless
-            env.declare_prefix(long_name, value.clone(), true)?;
+            if long_name.is_none() && short_name.is_none() {
+                return Err(Error::ForbiddenName("_".to_string()));
+            }
+
+            // A prefix without a short name can't be used with short unit names and vice versa
+            if let Some(name) = long_name {
+                env.declare_prefix(name, value.clone(), true)?;
+            }
             if let Some(name) = short_name {
                 env.declare_prefix(name, value, false)?;
             }
             Ok(Value::Nothing)
         }
+        Expr::PrefixDecls(prefixes) => {
+            for prefix in prefixes {
+                eval(prefix, env)?;
+            }
+            Ok(Value::Nothing)
+        }
         Expr::UnaryOp(op, expr) => {
             let value = eval(expr, env)?;
             match op {
@@ -530,32 +1056,27 @@ pub fn eval((expr, _): &Spanned<Expr>, env: &mut Environment) -> Result<Value, E
 /// Given a Quantity get the best matching unit to display the quantity as.
 /// Returns a new quantity which might be rescaled if there is no perfect match and
 /// long and short name of the unit.
-pub fn format_unit(quantity: Quantity, env: &Environment) -> (Quantity, (String, Option<String>)) {
+pub fn format_unit(quantity: Quantity, env: &Environment) -> (Quantity, UnitName) {
+    // A plain number is never given a name, even if a dimensionless unit like "unit one = 1" exists
+    if quantity.unit.is_unitless() {
+        return (quantity, (String::new(), None));
+    }
+
     let Quantity { number, unit } = &quantity;
     let Unit(scale, base_units) = unit;
 
-    let matches = env.get_unit_names(&base_units);
-
-    // Compare the scale of this unit with the scale used in our Quantity,
-    // is there a named unit with the same scale?
-
-    let unit_name = matches.iter().find_map(|unit_name @ (long_name, _)| {
-        let Ok(Unit(other_scale, _)) = env.get_unit(&long_name) else {
-            return None;
-        };
-
-        let diff = Number::abs(other_scale - scale.clone());
-
-        if diff != Number::zero() {
-            return None;
-        }
-
-        Some(unit_name)
-    });
+    // Dimensionless units are only used when the scale matches, so that a preferred
+    // percent does not turn every dozen into a percentage
+    let unit_name = if base_units.is_empty() {
+        env.unit_name(unit)
+    } else {
+        env.preferred_unit(base_units)
+            .or_else(|| env.unit_name(unit))
+    };
 
     match unit_name {
-        Some(names @ (ref long_name, _)) => {
-            let Unit(target_scale, _) = env.get_unit(&long_name).unwrap();
+        Some(names) => {
+            let Unit(target_scale, _) = env.get_unit(&names.0).unwrap();
 
             // Now, we might need to rescale the original quantity to fit we the unit
             // that we have selected.
@@ -564,7 +1085,7 @@ pub fn format_unit(quantity: Quantity, env: &Environment) -> (Quantity, (String,
                 unit: Unit(target_scale, base_units.clone()),
             };
 
-            (rescaled_quantity, names.clone())
+            (rescaled_quantity, names)
         }
 
         // If we did not find a matching named unit, just rescale the quantity and present it in base units
@@ -582,6 +1103,112 @@ pub fn format_unit(quantity: Quantity, env: &Environment) -> (Quantity, (String,
     }
 }
 
+/// Write a quantity in engineering notation, where the exponent is a multiple of three. If there
+/// is a prefix for the power of ten it is used with the unit instead, so 1500 m is "1.5 km" rather
+/// than "1.5 × 10^3 m", while a quantity without a fitting prefix is written like "1.5 × 10^6 m".
+pub fn format_engineering(quantity: Quantity, env: &Environment) -> String {
+    let (quantity, (long_name, short_name)) = format_unit(quantity, env);
+    let (mantissa, exp) = quantity.number.engineering();
+
+    // Only named units can be prefixed, not something like "m/s"
+    let is_named = env.unit_name(&quantity.unit).is_some();
+    let (name, is_long_name) = match short_name {
+        Some(short_name) => (short_name, false),
+        None => (long_name, true),
+    };
+
+    let prefix = Some(exp)
+        .filter(|exp| *exp != 0 && is_named)
+        .and_then(|exp| env.prefix_for_power_of_ten(exp, is_long_name))
+        .filter(|prefix| env.get_unit(&format!("{prefix}{name}")).is_ok());
+
+    let number = match (prefix.is_some(), exp) {
+        (true, _) | (false, 0) => mantissa,
+        (false, exp) => format!("{mantissa} × 10^{exp}"),
+    };
+    [number, format!("{}{name}", prefix.unwrap_or_default())]
+        .join(" ")
+        .trim_end()
+        .to_string()
+}
+
+/// Evaluate a program like [`eval`] but call the observer with each top level statement and its
+/// result as soon as it has been evaluated, useful for streaming output. Stops at the first error.
+pub fn eval_program_with_observer(
+    program: &Spanned<Expr>,
+    env: &mut Environment,
+    mut observer: impl FnMut(&Spanned<Expr>, &Result<Value, Error>),
+) -> Result<Value, Error> {
+    let mut result = Ok(Value::Nothing);
+    for statement in statements(program) {
+        result = eval(statement, env);
+        observer(statement, &result);
+        if result.is_err() {
+            break;
+        }
+    }
+    result
+}
+
+/// Evaluate every top level statement of a program and return all of their results in order.
+/// Unlike [`eval`] this continues past errors, a statement that fails to evaluate simply
+/// declares nothing, so the following statements might fail as well if they depend on it.
+pub fn eval_all(program: &Spanned<Expr>, env: &mut Environment) -> Vec<Result<Value, Error>> {
+    statements(program)
+        .iter()
+        .map(|statement| eval(statement, env))
+        .collect()
+}
+
+/// The top level statements of a program, or just the expression itself if it is not a program
+fn statements(program: &Spanned<Expr>) -> &[Spanned<Expr>] {
+    match program {
+        (Expr::Program(statements), _) => statements.as_slice(),
+        statement => std::slice::from_ref(statement),
+    }
+}
+
+/// A number of arguments for error messages, "1 argument" or "2 arguments"
+fn count_arguments(count: usize) -> String {
+    match count {
+        1 => "1 argument".to_string(),
+        count => format!("{count} arguments"),
+    }
+}
+
+/// The kind of value (see [`Value::kind`]) an expression evaluates to, if it is clear from the syntax
+fn syntactic_kind(expr: &Expr) -> Option<&'static str> {
+    match expr {
+        Expr::Literal(Literal::Bool(_)) => Some("bool"),
+        Expr::Literal(Literal::Quantity(..) | Literal::Rational(..)) => Some("quantity"),
+        Expr::BinOp(BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div | BinOp::Pow, ..) => {
+            Some("quantity")
+        }
+        Expr::BinOp(
+            BinOp::Equal
+            | BinOp::NotEqual
+            | BinOp::Lt
+            | BinOp::Gt
+            | BinOp::Gte
+            | BinOp::Lte
+            | BinOp::And
+            | BinOp::Or
+            | BinOp::Xor,
+            ..,
+        ) => Some("bool"),
+        Expr::UnaryOp(UnaryOp::Negate, _) | Expr::Conversion(..) => Some("quantity"),
+        Expr::UnaryOp(UnaryOp::Not, _) => Some("bool"),
+        Expr::Block(expressions) => expressions
+            .last()
+            .and_then(|(expr, _)| syntactic_kind(expr)),
+        Expr::If(_, a, b) => match (syntactic_kind(&a.0), syntactic_kind(&b.0)) {
+            (Some(a), Some(b)) if a == b => Some(a),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
 fn eval_block(expressions: &Vec<Spanned<Expr>>, env: &mut Environment) -> Result<Value, Error> {
     for (i, expr) in expressions.iter().enumerate() {
         // The last expression of the block will be return value for the block expression itself
@@ -598,8 +1225,11 @@ fn eval_literal(literal: &Literal, env: &mut Environment) -> Result<Value, Error
         Literal::Nothing => Value::Nothing,
         Literal::Bool(b) => Value::Bool(*b),
         Literal::Quantity(number, name) => {
-            let unit = if let Some(name) = name {
-                env.get_unit(name)?
+            let unit = if let Some((name, span)) = name {
+                // Point out the unit itself if it can't be found, "5 furlong" is much
+                // more likely to be a typo or a missing declaration than a bad number
+                env.get_unit(name)
+                    .map_err(|_| Error::UnknownUnit(name.clone(), span.clone()))?
             } else {
                 Unit::unitless()
             };
@@ -615,5 +1245,31 @@ fn eval_literal(literal: &Literal, env: &mut Environment) -> Result<Value, Error
                 unit,
             })
         }
+        Literal::Rational(numerator, denominator) => Value::Quantity(Quantity {
+            number: Number::Exact(BigRational::new((*numerator).into(), (*denominator).into())),
+            unit: Unit::unitless(),
+        }),
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn value_predicates() {
+        let mut env = Environment::default();
+        let mut eval_str = |source| eval(&parse(source).unwrap(), &mut env).unwrap();
+
+        assert!(eval_str("nothing").is_nothing());
+        assert!(eval_str("true").is_bool());
+        assert!(eval_str("5 m").is_quantity());
+        assert!(eval_str("f(x) = x").is_function());
+        assert!(eval_str("count").is_function());
+
+        assert!(!eval_str("5 m").is_nothing());
+        assert!(!eval_str("false").is_quantity());
+        assert!(!eval_str("nothing").is_bool());
+        assert!(!eval_str("1").is_function());
+    }
+}
@@ -13,6 +13,7 @@ assert_eq!(value.to_string(), "42".to_string());
 */
 mod error;
 mod eval;
+mod natives;
 pub mod number;
 #[allow(dead_code)]
 mod trie;
@@ -22,6 +23,7 @@ pub use error::{report_error, Error};
 pub use eval::*;
 pub use syntax::expr::{Expr, Spanned};
 use syntax::parser;
+pub use syntax::parser::ends_with_operator;
 
 pub fn parse(source: &str) -> Result<Spanned<Expr>, Vec<Error>> {
     parser::parse(source).map_err(|errors| errors.into_iter().map(Error::Parsing).collect())
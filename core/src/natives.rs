@@ -0,0 +1,684 @@
+//! Functions implemented in Rust which are available in every
+//! environment that includes the prelude.
+
+use crate::{
+    number::Number,
+    units::{Quantity, Unit},
+    Environment, Error, Value,
+};
+use num::{BigInt, Signed, ToPrimitive};
+
+pub(crate) fn declare_natives(env: &mut Environment) {
+    env.declare_native("count", 1..=1, count);
+    env.declare_native("hex", 1..=2, |arguments| radix(arguments, "hex", 16, "0x"));
+    env.declare_native("bin", 1..=2, |arguments| radix(arguments, "bin", 2, "0b"));
+    env.declare_native("exact", 1..=1, exact);
+    env.declare_native("rationalize", 2..=2, rationalize);
+    env.declare_native("convert", 2..=2, convert);
+    env.declare_native("square", 1..=1, |arguments| power(arguments, "square", 2));
+    env.declare_native("cube", 1..=1, |arguments| power(arguments, "cube", 3));
+    env.declare_native("reciprocal", 1..=1, reciprocal);
+    env.declare_native("dimension", 1..=1, dimension);
+    env.declare_native("range", 3..=3, range);
+    env.declare_native("integrate", 3..=3, integrate);
+    env.declare_native("derivative", 2..=2, derivative);
+    env.declare_native("solve", 2..=3, solve);
+}
+
+/// The largest number of values `range` creates, since they are all kept in memory
+const MAX_RANGE_VALUES: usize = 100_000;
+
+/// The number of intervals used by `integrate`, Simpson's rule requires it to be even
+const INTEGRATION_INTERVALS: i64 = 1000;
+
+/// The number of iterations `solve` tries before giving up
+const SOLVE_ITERATIONS: usize = 100;
+
+/// How small a step `solve` needs to take to consider a root found, relative to the size of the
+/// root or to the size of the guess (or interval), so that roots at or near zero can be found too
+const SOLVE_TOLERANCE: f64 = 1e-12;
+
+/// Create a dimensionless quantity value
+fn dimensionless(number: Number) -> Value {
+    Value::Quantity(Quantity {
+        number,
+        unit: Unit::unitless(),
+    })
+}
+
+/// Get the value of a dimensionless quantity which must be an integer
+fn integer(value: &Value, fn_name: &str) -> Result<BigInt, Error> {
+    let quantity = value.expect_quantity(fn_name)?;
+    if !quantity.unit.1.is_empty() {
+        return Err(value.invalid(fn_name, "a number without a unit"));
+    }
+
+    quantity
+        .normalize()
+        .number
+        .as_bigint()
+        .ok_or_else(|| value.invalid(fn_name, "an integer"))
+}
+
+/// The number of elements in a list or characters in a string
+fn count(arguments: &[Value]) -> Result<Value, Error> {
+    let count = match &arguments[0] {
+        Value::List(values) => values.len(),
+        Value::String(s) => s.chars().count(),
+        value => return Err(value.unexpected("count", "a list or a string")),
+    };
+    Ok(dimensionless(Number::new(count as i64)))
+}
+
+/// Pin an approximate quantity to an exact rational
+fn exact(arguments: &[Value]) -> Result<Value, Error> {
+    let Quantity { number, unit } = arguments[0].expect_quantity("exact")?;
+    Ok(Value::Quantity(Quantity {
+        number: number.try_into_exact(),
+        unit,
+    }))
+}
+
+/// The closest fraction to a quantity with at most the given denominator,
+/// `rationalize(0.333333, 10)` is `1/3`
+fn rationalize(arguments: &[Value]) -> Result<Value, Error> {
+    let Quantity { number, unit } = arguments[0].expect_quantity("rationalize")?;
+    let max_denominator = integer(&arguments[1], "rationalize")?
+        .to_u64()
+        .filter(|max_denominator| *max_denominator > 0)
+        .ok_or_else(|| arguments[1].invalid("rationalize", "a positive denominator"))?;
+
+    Ok(Value::Quantity(Quantity {
+        number: number.rationalize(max_denominator),
+        unit,
+    }))
+}
+
+/// Convert a quantity into another unit, the function form of "3 km in meter"
+fn convert(arguments: &[Value]) -> Result<Value, Error> {
+    let quantity = arguments[0].expect_quantity("convert")?;
+    let Quantity { number, unit } = arguments[1].expect_quantity("convert")?;
+
+    // Just like with "in", conversions like "convert(1337 km, 20 meter)" are not supported
+    if number != Number::one() {
+        return Err(Error::InvalidType);
+    }
+
+    quantity
+        .try_convert(unit)
+        .map(Value::Quantity)
+        .ok_or(Error::InvalidUnitOperation)
+}
+
+/// Raise a quantity to an integer power, for example `square(3 m)` is `9 m^2`
+fn power(arguments: &[Value], fn_name: &str, exp: i32) -> Result<Value, Error> {
+    Ok(Value::Quantity(
+        arguments[0].expect_quantity(fn_name)?.powi(exp),
+    ))
+}
+
+/// One divided by a quantity, `reciprocal(4 s)` is `1/4 1/s`
+fn reciprocal(arguments: &[Value]) -> Result<Value, Error> {
+    let Quantity { number, unit } = arguments[0].expect_quantity("reciprocal")?;
+    if number == Number::zero() {
+        return Err(arguments[0].invalid("reciprocal", "a non-zero quantity"));
+    }
+
+    Ok(Value::Quantity(Quantity {
+        number: Number::one() / number,
+        unit: unit.inverse(),
+    }))
+}
+
+/// Describe the dimension of a quantity in terms of its base units
+fn dimension(arguments: &[Value]) -> Result<Value, Error> {
+    Ok(Value::String(
+        arguments[0].expect_quantity("dimension")?.unit.dimension(),
+    ))
+}
+
+/// All values from start to stop (inclusive) separated by a step. Every point is computed
+/// as `start + i * step` rather than by repeatedly adding the step, since that would build
+/// up large denominators for exact numbers and rounding errors for approximate ones.
+fn range(arguments: &[Value]) -> Result<Value, Error> {
+    let Quantity {
+        number: start,
+        unit,
+    } = arguments[0].expect_quantity("range")?;
+
+    // Express the stop and step in the same unit as the start
+    let in_unit = |value: &Value| {
+        value
+            .expect_quantity("range")?
+            .try_convert(unit.clone())
+            .map(|quantity| quantity.number)
+            .ok_or(Error::InvalidUnitOperation)
+    };
+    let stop = in_unit(&arguments[1])?;
+    let step = in_unit(&arguments[2])?;
+
+    if step == Number::zero() {
+        return Err(Error::InvalidType);
+    }
+
+    let steps = match (stop - start.clone()) / step.clone() {
+        Number::Exact(n) => n.floor().to_integer().to_i64(),
+        Number::Approx(n) => n.floor().to_i64(),
+    }
+    .ok_or(Error::InvalidType)?;
+
+    if steps >= MAX_RANGE_VALUES as i64 {
+        return Err(Error::TooManyValues("range".to_string(), MAX_RANGE_VALUES));
+    }
+
+    Ok(Value::List(
+        (0..=steps)
+            .map(|i| {
+                Value::Quantity(Quantity {
+                    number: start.clone() + Number::new(i) * step.clone(),
+                    unit: unit.clone(),
+                })
+            })
+            .collect(),
+    ))
+}
+
+/// Call a function of one quantity, which must give a quantity back
+fn sample(
+    f: &impl Fn(&[Value]) -> Result<Value, Error>,
+    fn_name: &str,
+    x: f64,
+    unit: &Unit,
+) -> Result<Quantity, Error> {
+    let x = Value::Quantity(Quantity {
+        number: Number::Approx(x),
+        unit: unit.clone(),
+    });
+    f(&[x])?.expect_quantity(fn_name)
+}
+
+/// The definite integral of a function from a to b using Simpson's rule, `integrate(f, 0 s, 2 s)`.
+/// The unit of the result is the unit of the function values multiplied by the unit of the bounds.
+fn integrate(arguments: &[Value]) -> Result<Value, Error> {
+    let f = arguments[0].expect_callable("integrate")?;
+    let Quantity { number: a, unit } = arguments[1].expect_quantity("integrate")?;
+    let b = arguments[2]
+        .expect_quantity("integrate")?
+        .try_convert(unit.clone())
+        .ok_or(Error::InvalidUnitOperation)?
+        .number;
+
+    let (a, b) = (a.to_f64(), b.to_f64());
+    let h = (b - a) / INTEGRATION_INTERVALS as f64;
+
+    // All of the function values are expressed in the unit of the first one
+    let first = sample(&f, "integrate", a, &unit)?;
+    let mut sum = first.number.to_f64();
+    for i in 1..=INTEGRATION_INTERVALS {
+        let weight = match i {
+            INTEGRATION_INTERVALS => 1.0,
+            i if i % 2 == 1 => 4.0,
+            _ => 2.0,
+        };
+        let y = sample(&f, "integrate", a + i as f64 * h, &unit)?
+            .try_convert(first.unit.clone())
+            .ok_or(Error::InvalidUnitOperation)?;
+        sum += weight * y.number.to_f64();
+    }
+
+    Ok(Value::Quantity(Quantity {
+        number: Number::Approx(sum * h / 3.0),
+        unit: first.unit * unit,
+    }))
+}
+
+/// The derivative of a function at a point using a central difference, `derivative(f, 2 s)`.
+/// The unit of the result is the unit of the function values divided by the unit of the point.
+fn derivative(arguments: &[Value]) -> Result<Value, Error> {
+    let f = arguments[0].expect_callable("derivative")?;
+    let Quantity { number: x, unit } = arguments[1].expect_quantity("derivative")?;
+
+    // A step of about the cube root of the machine epsilon balances the truncation
+    // error of the central difference against the rounding errors
+    let x = x.to_f64();
+    let h = f64::EPSILON.cbrt() * x.abs().max(1.0);
+
+    let before = sample(&f, "derivative", x - h, &unit)?;
+    let after = sample(&f, "derivative", x + h, &unit)?
+        .try_convert(before.unit.clone())
+        .ok_or(Error::InvalidUnitOperation)?;
+    let slope = (after.number.to_f64() - before.number.to_f64()) / (2.0 * h);
+
+    Ok(Value::Quantity(Quantity {
+        number: Number::Approx(slope),
+        unit: before.unit / unit,
+    }))
+}
+
+/// Find a root of a function, either using Newton's method starting from a guess, `solve(f, 1 s)`,
+/// or using bisection within an interval where the function changes sign, `solve(f, 0 s, 5 s)`.
+/// The root is given in the unit of the guess or the start of the interval.
+fn solve(arguments: &[Value]) -> Result<Value, Error> {
+    let f = arguments[0].expect_callable("solve")?;
+    let Quantity {
+        number: start,
+        unit,
+    } = arguments[1].expect_quantity("solve")?;
+
+    // All of the function values are expressed in the unit of the first one
+    let start = start.to_f64();
+    let value_unit = sample(&f, "solve", start, &unit)?.unit;
+    let value_at = |x| -> Result<f64, Error> {
+        let y = sample(&f, "solve", x, &unit)?
+            .try_convert(value_unit.clone())
+            .ok_or(Error::InvalidUnitOperation)?;
+        Ok(y.number.to_f64())
+    };
+
+    let root = match arguments.get(2) {
+        Some(end) => {
+            let end = end
+                .expect_quantity("solve")?
+                .try_convert(unit.clone())
+                .ok_or(Error::InvalidUnitOperation)?;
+            bisection(value_at, start, end.number.to_f64())?
+        }
+        None => newton(value_at, start)?,
+    };
+
+    Ok(Value::Quantity(Quantity {
+        number: Number::Approx(root),
+        unit,
+    }))
+}
+
+fn newton(value_at: impl Fn(f64) -> Result<f64, Error>, guess: f64) -> Result<f64, Error> {
+    let mut x = guess;
+    for _ in 0..SOLVE_ITERATIONS {
+        let y = value_at(x)?;
+        if y == 0.0 {
+            return Ok(x);
+        }
+
+        // The step follows the size of x, a fixed step would swamp the slope with
+        // the error of the central difference close to a root at zero
+        let h = f64::EPSILON.cbrt() * if x == 0.0 { 1.0 } else { x.abs() };
+        let slope = (value_at(x + h)? - value_at(x - h)?) / (2.0 * h);
+        if slope == 0.0 {
+            return Err(Error::NoSolution(
+                "solve".to_string(),
+                "the derivative is zero near the guess",
+            ));
+        }
+
+        let step = y / slope;
+        x -= step;
+        if !x.is_finite() {
+            break;
+        }
+        if step.abs() <= SOLVE_TOLERANCE * x.abs().max(guess.abs()) {
+            return Ok(x);
+        }
+    }
+
+    Err(Error::NoSolution(
+        "solve".to_string(),
+        "Newton's method did not converge, try another guess or an interval",
+    ))
+}
+
+fn bisection(value_at: impl Fn(f64) -> Result<f64, Error>, lo: f64, hi: f64) -> Result<f64, Error> {
+    let scale = lo.abs().max(hi.abs());
+    let (mut lo, mut hi) = (lo, hi);
+    let (mut y_lo, y_hi) = (value_at(lo)?, value_at(hi)?);
+    if y_lo == 0.0 {
+        return Ok(lo);
+    }
+    if y_hi == 0.0 {
+        return Ok(hi);
+    }
+    if y_lo.signum() == y_hi.signum() {
+        return Err(Error::NoSolution(
+            "solve".to_string(),
+            "the function must have different signs at the ends of the interval",
+        ));
+    }
+
+    for _ in 0..SOLVE_ITERATIONS {
+        let mid = (lo + hi) / 2.0;
+        let y_mid = value_at(mid)?;
+        let converged = (hi - lo).abs() <= SOLVE_TOLERANCE * scale || mid == lo || mid == hi;
+        if y_mid == 0.0 || converged {
+            return Ok(mid);
+        }
+
+        if y_mid.signum() == y_lo.signum() {
+            (lo, y_lo) = (mid, y_mid);
+        } else {
+            hi = mid;
+        }
+    }
+
+    Err(Error::NoSolution(
+        "solve".to_string(),
+        "the bisection did not converge",
+    ))
+}
+
+/// Write an integer in the given base, zero padded to an optional minimum number of digits.
+/// Negative numbers get a leading minus sign rather than being written in two's complement
+/// since there is no fixed word size to base the complement on, so `hex(-15, 4)` is `-0x000F`.
+fn radix(arguments: &[Value], fn_name: &str, radix: u32, prefix: &str) -> Result<Value, Error> {
+    let number = integer(&arguments[0], fn_name)?;
+    let width = match arguments.get(1) {
+        Some(width) => integer(width, fn_name)?
+            .to_usize()
+            .ok_or_else(|| width.invalid(fn_name, "a non-negative width"))?,
+        None => 0,
+    };
+
+    let sign = if number.is_negative() { "-" } else { "" };
+    let digits = number.magnitude().to_str_radix(radix).to_uppercase();
+    Ok(Value::String(format!("{sign}{prefix}{digits:0>width$}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::units::BaseUnit;
+    use num::rational::Ratio;
+
+    fn int(n: i64) -> Value {
+        dimensionless(Number::new(n))
+    }
+
+    fn number(n: Number) -> Value {
+        dimensionless(n)
+    }
+
+    fn string(s: &str) -> Value {
+        Value::String(s.to_string())
+    }
+
+    #[test]
+    fn count_lists() {
+        let list = Value::List(vec![Value::Bool(true), int(2)]);
+        assert_eq!(count(&[list]).unwrap(), int(2));
+        assert_eq!(count(&[Value::List(Vec::new())]).unwrap(), int(0));
+        assert_eq!(count(&[string("åäö")]).unwrap(), int(3));
+        assert!(matches!(
+            count(&[int(1)]),
+            Err(Error::UnexpectedType(Some(name), _, "quantity")) if name == "count"
+        ));
+    }
+
+    #[test]
+    fn exact_quantity() {
+        let half = Quantity {
+            number: Number::Approx(0.5),
+            unit: Unit::unitless(),
+        };
+        let Value::Quantity(result) = exact(&[Value::Quantity(half)]).unwrap() else {
+            panic!("Expected a quantity");
+        };
+        assert_eq!(result.number, Number::one() / Number::new(2));
+        assert!(exact(&[Value::Bool(true)]).is_err());
+    }
+
+    #[test]
+    fn rationalize_quantity() {
+        let third = number(Number::one() / Number::new(3));
+        assert_eq!(
+            rationalize(&[number(Number::Approx(0.333333)), int(10)]).unwrap(),
+            third
+        );
+        assert!(rationalize(&[number(Number::Approx(0.5)), int(0)]).is_err());
+        assert!(rationalize(&[number(Number::Approx(0.5)), int(-2)]).is_err());
+    }
+
+    #[test]
+    fn convert_units() {
+        let unit = |long_name: &str, short_name: &str| {
+            let base_unit = BaseUnit(long_name.to_string(), Some(short_name.to_string()));
+            Unit(Number::one(), [(base_unit, Ratio::new(1, 1))].into())
+        };
+        let quantity = |number: i64, unit: Unit| {
+            Value::Quantity(Quantity {
+                number: Number::new(number),
+                unit,
+            })
+        };
+        let meter = unit("meter", "m");
+        let kilometer = meter.clone().rescaled(Number::new(1000));
+
+        let Value::Quantity(result) =
+            convert(&[quantity(3, kilometer.clone()), quantity(1, meter.clone())]).unwrap()
+        else {
+            panic!("Expected a quantity");
+        };
+        assert_eq!(result.number, Number::new(3000));
+        assert_eq!(result.unit, meter);
+
+        assert!(matches!(
+            convert(&[quantity(3, meter.clone()), quantity(1, unit("second", "s"))]),
+            Err(Error::InvalidUnitOperation)
+        ));
+        assert!(convert(&[quantity(3, meter), quantity(20, kilometer)]).is_err());
+    }
+
+    #[test]
+    fn range_endpoints() {
+        let Value::List(values) =
+            range(&[int(0), int(1), number(Number::new(1) / Number::new(1000))]).unwrap()
+        else {
+            panic!("Expected a list");
+        };
+        assert_eq!(values.len(), 1001);
+        assert_eq!(values[0], int(0));
+        assert_eq!(values[500], number(Number::new(1) / Number::new(2)));
+        assert_eq!(values[1000], int(1));
+        assert!(matches!(
+            values[1000],
+            Value::Quantity(Quantity {
+                number: Number::Exact(_),
+                ..
+            })
+        ));
+
+        // The stop is not included unless it is hit exactly
+        let Value::List(values) = range(&[int(0), int(10), int(3)]).unwrap() else {
+            panic!("Expected a list");
+        };
+        assert_eq!(values, vec![int(0), int(3), int(6), int(9)]);
+
+        // Counting down
+        let Value::List(values) = range(&[int(2), int(0), int(-1)]).unwrap() else {
+            panic!("Expected a list");
+        };
+        assert_eq!(values, vec![int(2), int(1), int(0)]);
+
+        assert_eq!(
+            range(&[int(0), int(-1), int(1)]).unwrap(),
+            Value::List(Vec::new())
+        );
+        assert!(range(&[int(0), int(1), int(0)]).is_err());
+
+        // Too many values to keep in memory
+        let tiny_step = number(Number::new(1) / Number::new(1_000_000_000));
+        assert!(matches!(
+            range(&[int(0), int(1000), tiny_step]),
+            Err(Error::TooManyValues(name, MAX_RANGE_VALUES)) if name == "range"
+        ));
+        let Value::List(values) = range(&[int(1), int(MAX_RANGE_VALUES as i64), int(1)]).unwrap()
+        else {
+            panic!("Expected a list");
+        };
+        assert_eq!(values.len(), MAX_RANGE_VALUES);
+    }
+
+    /// Evaluate some source code and get the value of the last expression
+    fn value(source: &str) -> Value {
+        let mut env = Environment::default();
+        crate::eval(&crate::parse(source).unwrap(), &mut env).unwrap()
+    }
+
+    /// Evaluate a quantity and check that it is close to the expected one
+    fn assert_close(value: Value, expected: &str) {
+        let mut env = Environment::default();
+        let expected = crate::eval(&crate::parse(expected).unwrap(), &mut env)
+            .unwrap()
+            .quantity()
+            .unwrap();
+        let Quantity { number, .. } = value
+            .quantity()
+            .unwrap()
+            .try_convert(expected.unit)
+            .expect("Expected quantities of the same dimension");
+        let difference = number.to_f64() - expected.number.to_f64();
+        assert!(difference.abs() < 1e-6, "off by {difference}");
+    }
+
+    #[test]
+    fn integrals() {
+        let square = value("f(x) = x * x");
+        assert_close(integrate(&[square.clone(), int(0), int(1)]).unwrap(), "1/3");
+        assert_close(integrate(&[square, int(3), int(0)]).unwrap(), "-9");
+
+        let cube = value("f(x) = x * x * x / 1 m");
+        assert_close(
+            integrate(&[cube, value("2 m"), value("4 m")]).unwrap(),
+            "60 m^3",
+        );
+
+        let speed = value("v(t) = 3 m/s^2 * t");
+        assert_close(
+            integrate(&[speed.clone(), value("0 s"), value("2000 ms")]).unwrap(),
+            "6 m",
+        );
+        assert!(matches!(
+            integrate(&[speed.clone(), value("0 s"), value("2 m")]),
+            Err(Error::InvalidUnitOperation)
+        ));
+        assert!(integrate(&[int(2), int(0), int(1)]).is_err());
+    }
+
+    #[test]
+    fn derivatives() {
+        let cube = value("f(x) = x * x * x");
+        assert_close(derivative(&[cube, int(2)]).unwrap(), "12");
+
+        let position = value("p(t) = 5 m/s^2 * t * t");
+        assert_close(derivative(&[position, value("2 s")]).unwrap(), "20 m/s");
+
+        // The function must give quantities
+        let predicate = value("f(x) = x > 2");
+        assert!(matches!(
+            derivative(&[predicate, int(2)]),
+            Err(Error::UnexpectedType(..))
+        ));
+        assert!(matches!(
+            derivative(&[int(2), int(2)]),
+            Err(Error::UnexpectedType(Some(name), "a function", "quantity")) if name == "derivative"
+        ));
+    }
+
+    #[test]
+    fn quadratic_roots() {
+        let quadratic = value("f(x) = x * x - 2");
+        assert_close(
+            solve(&[quadratic.clone(), int(1)]).unwrap(),
+            "1.41421356237",
+        );
+        assert_close(
+            solve(&[quadratic.clone(), int(-1)]).unwrap(),
+            "-1.41421356237",
+        );
+        assert_close(
+            solve(&[quadratic.clone(), int(0), int(5)]).unwrap(),
+            "1.41421356237",
+        );
+
+        // The time it takes to fall 20 m, given in the unit of the guess
+        let fall = value("f(t) = 5 m/s^2 * t * t - 20 m");
+        let Value::Quantity(time) = solve(&[fall.clone(), value("1 s")]).unwrap() else {
+            panic!("Expected a quantity");
+        };
+        assert_close(Value::Quantity(time.clone()), "2 s");
+        assert_eq!(time.unit, value("1 s").quantity().unwrap().unit);
+        assert_close(
+            solve(&[fall.clone(), value("0 s"), value("5000 ms")]).unwrap(),
+            "2 s",
+        );
+        assert!(matches!(
+            solve(&[fall, value("0 s"), value("5 m")]),
+            Err(Error::InvalidUnitOperation)
+        ));
+
+        // Roots at zero, including a triple root
+        let square = value("f(x) = x * x");
+        assert_close(solve(&[square.clone(), int(1)]).unwrap(), "0");
+        assert_close(solve(&[square, int(-5)]).unwrap(), "0");
+        let cube = value("f(x) = x * x * x");
+        assert_close(solve(&[cube.clone(), int(1)]).unwrap(), "0");
+        assert_close(solve(&[cube, int(-1), int(2)]).unwrap(), "0");
+
+        // A double root away from zero
+        let double = value("f(x) = (x - 1 m) * (x - 1 m)");
+        assert_close(solve(&[double, value("3 m")]).unwrap(), "1 m");
+
+        // No real roots
+        let positive = value("f(x) = x * x + 1");
+        assert!(matches!(
+            solve(&[positive.clone(), int(3)]),
+            Err(Error::NoSolution(..))
+        ));
+        assert!(matches!(
+            solve(&[positive, int(-3), int(3)]),
+            Err(Error::NoSolution(..))
+        ));
+    }
+
+    #[test]
+    fn radix_padding() {
+        assert_eq!(radix(&[int(15)], "hex", 16, "0x").unwrap(), string("0xF"));
+        assert_eq!(
+            radix(&[number(Number::Approx(15.0))], "hex", 16, "0x").unwrap(),
+            string("0xF")
+        );
+        assert_eq!(
+            radix(&[int(15), int(2)], "hex", 16, "0x").unwrap(),
+            string("0x0F")
+        );
+        assert_eq!(
+            radix(&[int(255), int(1)], "hex", 16, "0x").unwrap(),
+            string("0xFF")
+        );
+        assert_eq!(
+            radix(&[int(5), int(8)], "bin", 2, "0b").unwrap(),
+            string("0b00000101")
+        );
+        assert!(radix(
+            &[Value::Quantity(Quantity {
+                number: Number::from_decimal_str("1.5"),
+                unit: Unit::unitless()
+            })],
+            "hex",
+            16,
+            "0x"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn radix_negative() {
+        assert_eq!(radix(&[int(-15)], "hex", 16, "0x").unwrap(), string("-0xF"));
+        assert_eq!(
+            radix(&[int(-15), int(4)], "hex", 16, "0x").unwrap(),
+            string("-0x000F")
+        );
+        assert_eq!(
+            radix(&[int(-2), int(4)], "bin", 2, "0b").unwrap(),
+            string("-0b0010")
+        );
+    }
+}
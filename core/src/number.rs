@@ -1,10 +1,30 @@
 use num::{
     bigint::{BigInt, ToBigInt},
-    BigRational, ToPrimitive, Num,
+    rational::Ratio,
+    BigRational, One, Signed, ToPrimitive, Num, Zero,
 };
-use std::{fmt, ops, str::FromStr};
+use std::{cmp, fmt, ops, str::FromStr};
 
-#[derive(Debug, Clone, PartialEq, PartialOrd)]
+/// Ways of writing a number as text, see [`Number::format`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NumberStyle {
+    /// The exact fraction for exact numbers, like 1/3
+    Exact,
+    /// A decimal approximation, like 0.3333333333333333
+    Approx,
+    /// The exact fraction followed by a decimal approximation when the decimal
+    /// form would not terminate, 1/3 is "1/3 (≈ 0.3333333333333333)" but 1/2 is just "1/2"
+    ExactWithApprox,
+    /// A decimal approximation in engineering notation, where the exponent is a multiple of
+    /// three and the mantissa is between 1 and 1000, like "1.5 × 10^3"
+    Engineering,
+}
+
+/// The largest exponent (in magnitude) an exact number is raised to, larger powers are
+/// approximated since the exact result would take too long to compute and print
+const MAX_EXACT_EXPONENT: i32 = 10_000;
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Number {
     Exact(BigRational),
     Approx(f64),
@@ -67,10 +87,20 @@ impl Number {
     }
 
     pub fn abs(self) -> Self {
-        if self < Number::zero() {
-            -self
-        } else {
-            self
+        match self {
+            Exact(n) => Exact(n.abs()),
+            Approx(n) => Approx(n.abs()),
+        }
+    }
+
+    /// The sign of the number as -1, 0 or 1. Both zero and negative zero give 0, as does NaN.
+    pub fn signum(&self) -> i32 {
+        match self {
+            Exact(n) if n.is_positive() => 1,
+            Exact(n) if n.is_negative() => -1,
+            Approx(n) if *n > 0.0 => 1,
+            Approx(n) if *n < 0.0 => -1,
+            _ => 0,
         }
     }
 
@@ -89,6 +119,187 @@ impl Number {
             self
         }
     }
+
+    /// The approximate value of the number as a float
+    pub fn to_f64(&self) -> f64 {
+        match self {
+            Exact(n) => n.to_f64().expect("Cannot represent number as f64"),
+            Approx(n) => *n,
+        }
+    }
+
+    /// Raise the number to an integer power. Exact zero has no negative powers, so make
+    /// sure the number is non-zero before raising it to one.
+    pub fn powi(self, exp: i32) -> Self {
+        match self {
+            Exact(n) if exp.unsigned_abs() > MAX_EXACT_EXPONENT.unsigned_abs() => {
+                Exact(n).into_approx().powi(exp)
+            }
+            Exact(n) => Exact(n.pow(exp)),
+            Approx(n) => Approx(n.powi(exp)),
+        }
+    }
+
+    /// Raise the number to a rational power. Integer exponents keep exact numbers exact, as do
+    /// roots of exact perfect powers, so 8^(1/3) is exactly 2. Any other exponent gives an
+    /// approximate result.
+    pub fn pow_ratio(self, exp: Ratio<i64>) -> Self {
+        if let Some(exp) = exp.to_integer().to_i32().filter(|_| exp.is_integer()) {
+            return self.powi(exp);
+        }
+        if let Some(root) = self.exact_root(*exp.denom()) {
+            if let Some(numer) = exp.numer().to_i32() {
+                return root.powi(numer);
+            }
+        }
+        self.powf(Approx(*exp.numer() as f64 / *exp.denom() as f64))
+    }
+
+    /// The exact nth root of an exact number, if both its numerator and denominator
+    /// are perfect powers. Even roots of negative numbers have no (real) root.
+    fn exact_root(&self, n: i64) -> Option<Self> {
+        let Exact(value) = self else {
+            return None;
+        };
+        let n = n.to_i32().filter(|n| *n > 0)?;
+        if value.is_negative() && n % 2 == 0 {
+            return None;
+        }
+
+        let nth_root = |x: &BigInt| x.nth_root(n as u32);
+        let root = BigRational::new(nth_root(value.numer()), nth_root(value.denom()));
+        (root.pow(n) == *value).then_some(Exact(root))
+    }
+
+    /// Raise the number to any power, the result is always approximate
+    pub fn powf(self, exp: Self) -> Self {
+        Approx(self.to_f64().powf(exp.to_f64()))
+    }
+
+    /// Convert an approximate number into an exact rational using the exact binary value
+    /// of the float, so 0.5 becomes 1/2 but 0.1 becomes 3602879701896397/36028797018963968.
+    /// Exact numbers are left unchanged, as are NaN and infinities which have no exact form.
+    pub fn try_into_exact(self) -> Self {
+        match self {
+            Approx(n) => BigRational::from_float(n).map(Exact).unwrap_or(self),
+            exact => exact,
+        }
+    }
+
+    /// The closest fraction with a denominator of at most `max_denominator` (which is
+    /// treated as at least 1), found using the convergents of the continued fraction of
+    /// the number. This turns messy floats like 0.333333 back into 1/3 when given a small
+    /// enough bound. The result is always exact, except for NaN and infinities.
+    pub fn rationalize(&self, max_denominator: u64) -> Self {
+        let Exact(n) = self.clone().try_into_exact() else {
+            return self.clone();
+        };
+        let max_denominator = BigInt::from(max_denominator.max(1));
+        if n.denom() <= &max_denominator {
+            return Exact(n);
+        }
+
+        // Work with the magnitude so that the integer divisions round down
+        let (mut numer, mut denom) = (n.numer().abs(), n.denom().clone());
+        let (mut p0, mut q0, mut p1, mut q1) =
+            (BigInt::zero(), BigInt::one(), BigInt::one(), BigInt::zero());
+        loop {
+            let a = &numer / &denom;
+            let q2 = &q0 + &a * &q1;
+            if q2 > max_denominator {
+                break;
+            }
+            let p2 = &p0 + &a * &p1;
+            (p0, q0, p1, q1) = (p1, q1, p2, q2);
+            let remainder = &numer - &a * &denom;
+            (numer, denom) = (denom, remainder);
+        }
+
+        // The best approximation is either the last convergent or the
+        // semiconvergent with the largest denominator within the bound
+        let k = (&max_denominator - &q0) / &q1;
+        let semiconvergent = BigRational::new(&p0 + &k * &p1, &q0 + &k * &q1);
+        let convergent = BigRational::new(p1, q1);
+        let magnitude = n.abs();
+        let best = if (&semiconvergent - &magnitude).abs() < (&convergent - &magnitude).abs() {
+            semiconvergent
+        } else {
+            convergent
+        };
+
+        Exact(if n.is_negative() { -best } else { best })
+    }
+
+    /// The value as an integer, if it is one. Approximate numbers count as long as
+    /// they are exactly integers, so 3.0 is 3 but 2.5 and NaN are not integers.
+    pub fn as_bigint(&self) -> Option<BigInt> {
+        match self.clone().try_into_exact() {
+            Exact(n) if n.is_integer() => Some(n.to_integer()),
+            _ => None,
+        }
+    }
+
+    /// Like [`Number::as_bigint`] but also requires the integer to fit in an `i64`
+    pub fn as_i64(&self) -> Option<i64> {
+        self.as_bigint()?.to_i64()
+    }
+
+    /// Write the number in the given style. Approximate numbers look the same in every style
+    /// except for the engineering notation.
+    pub fn format(&self, style: NumberStyle) -> String {
+        match (self, style) {
+            (Exact(_), NumberStyle::Approx) => self.clone().into_approx().to_string(),
+            (_, NumberStyle::Engineering) => match self.engineering() {
+                (mantissa, 0) => mantissa,
+                (mantissa, exp) => format!("{mantissa} × 10^{exp}"),
+            },
+            (Exact(n), NumberStyle::ExactWithApprox) if !has_terminating_decimal(n) => {
+                format!("{self} (≈ {})", self.clone().into_approx())
+            }
+            _ => self.to_string(),
+        }
+    }
+
+    /// Split the (approximate) number into a mantissa between 1 and 1000 and an exponent which
+    /// is a multiple of three. The digits are taken from the shortest decimal form of the float
+    /// rather than by dividing it, which could introduce rounding errors like 1.5000000000000002.
+    pub fn engineering(&self) -> (String, i32) {
+        let n = self.to_f64();
+        if n == 0.0 || !n.is_finite() {
+            return (n.to_string(), 0);
+        }
+
+        // Like "1.5e3" for 1500
+        let scientific = format!("{:e}", n.abs());
+        let (mantissa, exp) = scientific.split_once('e').unwrap();
+        let exp: i32 = exp.parse().unwrap();
+
+        // Move the decimal point to the right until the exponent is a multiple of three
+        let shift = exp.rem_euclid(3) as usize;
+        let digits = format!("{:0<width$}", mantissa.replace('.', ""), width = shift + 1);
+        let (integer, fraction) = digits.split_at(shift + 1);
+
+        let sign = if n < 0.0 { "-" } else { "" };
+        let mantissa = if fraction.is_empty() {
+            format!("{sign}{integer}")
+        } else {
+            format!("{sign}{integer}.{fraction}")
+        };
+        (mantissa, exp - shift as i32)
+    }
+}
+
+/// A fraction can be written as a finite decimal number if the
+/// only prime factors of its (reduced) denominator are 2 and 5
+fn has_terminating_decimal(n: &BigRational) -> bool {
+    let mut denominator = n.denom().clone();
+    for factor in [2, 5] {
+        let factor = BigInt::from(factor);
+        while (&denominator % &factor).is_zero() {
+            denominator /= &factor;
+        }
+    }
+    denominator.is_one()
 }
 
 impl fmt::Display for Number {
@@ -102,6 +313,16 @@ impl fmt::Display for Number {
 
 use Number::*;
 
+impl cmp::PartialOrd for Number {
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        match (self, other) {
+            (Exact(a), Exact(b)) => a.partial_cmp(b),
+            // Like arithmetic, mixed forms are compared as approximate numbers
+            (a, b) => a.to_f64().partial_cmp(&b.to_f64()),
+        }
+    }
+}
+
 impl ops::Add for Number {
     type Output = Self;
 
@@ -164,3 +385,139 @@ impl ops::Div for Number {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn try_into_exact() {
+        let half = Number::one() / Number::new(2);
+        assert_eq!(Approx(0.5).try_into_exact(), half);
+        assert_eq!(half.clone().try_into_exact(), half);
+        assert_eq!(Approx(-3.0).try_into_exact(), Number::new(-3));
+        assert!(matches!(Approx(f64::NAN).try_into_exact(), Approx(_)));
+    }
+
+    #[test]
+    fn signs() {
+        assert_eq!(Number::new(-3).signum(), -1);
+        assert_eq!(Number::zero().signum(), 0);
+        assert_eq!((Number::one() / Number::new(3)).signum(), 1);
+        assert_eq!(Approx(-0.5).signum(), -1);
+        assert_eq!(Approx(-0.0).signum(), 0);
+        assert_eq!(Approx(f64::NAN).signum(), 0);
+
+        assert_eq!(Approx(-2.5).abs(), Approx(2.5));
+        assert_eq!(Number::new(-2).abs(), Number::new(2));
+    }
+
+    #[test]
+    fn mixed_comparisons() {
+        let third = Number::one() / Number::new(3);
+        assert!(Approx(0.3) < third);
+        assert!(Approx(0.4) > third);
+        assert!(Number::new(-1) < Approx(0.5));
+        assert!(Number::new(2) > Approx(-3.0));
+        assert_eq!(Number::new(2).partial_cmp(&Approx(f64::NAN)), None);
+    }
+
+    #[test]
+    fn exact_with_approx() {
+        let third = Number::one() / Number::new(3);
+        let half = Number::one() / Number::new(2);
+        assert_eq!(
+            third.format(NumberStyle::ExactWithApprox),
+            "1/3 (≈ 0.3333333333333333)"
+        );
+        assert_eq!(half.format(NumberStyle::ExactWithApprox), "1/2");
+        assert_eq!(Number::new(20).format(NumberStyle::ExactWithApprox), "20");
+        assert_eq!(Approx(0.25).format(NumberStyle::ExactWithApprox), "0.25");
+        assert_eq!(half.format(NumberStyle::Approx), "0.5");
+    }
+
+    #[test]
+    fn engineering() {
+        let engineering = |n: f64| Approx(n).format(NumberStyle::Engineering);
+        assert_eq!(engineering(1.5), "1.5");
+        assert_eq!(engineering(15.0), "15");
+        assert_eq!(engineering(150.0), "150");
+        assert_eq!(engineering(1500.0), "1.5 × 10^3");
+        assert_eq!(engineering(15000.0), "15 × 10^3");
+        assert_eq!(engineering(123456.0), "123.456 × 10^3");
+        assert_eq!(engineering(1.5e6), "1.5 × 10^6");
+        assert_eq!(engineering(0.15), "150 × 10^-3");
+        assert_eq!(engineering(0.015), "15 × 10^-3");
+        assert_eq!(engineering(0.0015), "1.5 × 10^-3");
+        assert_eq!(engineering(1.5e-7), "150 × 10^-9");
+        assert_eq!(engineering(-2500.0), "-2.5 × 10^3");
+        assert_eq!(engineering(0.0), "0");
+        // Exact numbers are approximated
+        assert_eq!(
+            (Number::new(7) / Number::new(2000)).format(NumberStyle::Engineering),
+            "3.5 × 10^-3"
+        );
+    }
+
+    #[test]
+    fn as_integer() {
+        assert_eq!(Number::new(-7).as_i64(), Some(-7));
+        assert_eq!(Approx(3.0).as_i64(), Some(3));
+        assert_eq!((Number::new(5) / Number::new(2)).as_i64(), None);
+        assert_eq!(Approx(2.5).as_i64(), None);
+        assert_eq!(Approx(f64::NAN).as_i64(), None);
+
+        let huge = Number::new(i64::MAX) * Number::new(10);
+        assert_eq!(huge.as_i64(), None);
+        assert_eq!(
+            huge.as_bigint(),
+            Some(BigInt::from(i64::MAX) * BigInt::from(10))
+        );
+    }
+
+    #[test]
+    fn rationalize() {
+        let ratio = |n, d| Number::new(n) / Number::new(d);
+        assert_eq!(Approx(0.333333).rationalize(10), ratio(1, 3));
+        assert_eq!(Approx(-0.333333).rationalize(10), ratio(-1, 3));
+        assert_eq!(Approx(std::f64::consts::PI).rationalize(10), ratio(22, 7));
+        assert_eq!(
+            Approx(std::f64::consts::PI).rationalize(1000),
+            ratio(355, 113)
+        );
+        assert_eq!(Approx(2.5).rationalize(1), Number::new(2));
+        assert_eq!(ratio(3, 8).rationalize(8), ratio(3, 8));
+        assert_eq!(ratio(3, 8).rationalize(5), ratio(2, 5));
+        assert!(matches!(Approx(f64::NAN).rationalize(10), Approx(_)));
+    }
+
+    #[test]
+    fn pow_ratio() {
+        let half = Number::one() / Number::new(2);
+        assert_eq!(Number::new(2).pow_ratio(Ratio::new(3, 1)), Number::new(8));
+        assert_eq!(Number::new(2).pow_ratio(Ratio::new(-1, 1)), half);
+        assert_eq!(Number::new(9).pow_ratio(Ratio::new(1, 2)), Number::new(3));
+        assert_eq!(Number::new(8).pow_ratio(Ratio::new(1, 3)), Number::new(2));
+        assert_eq!(Number::new(8).pow_ratio(Ratio::new(-1, 3)), half);
+        assert_eq!(Number::new(-8).pow_ratio(Ratio::new(2, 3)), Number::new(4));
+        assert_eq!(
+            (Number::new(4) / Number::new(9)).pow_ratio(Ratio::new(3, 2)),
+            Number::new(8) / Number::new(27)
+        );
+        // Without an exact root the result is approximate
+        assert_eq!(
+            Number::new(2).pow_ratio(Ratio::new(1, 2)),
+            Approx(std::f64::consts::SQRT_2)
+        );
+        assert!(matches!(Number::new(-4).pow_ratio(Ratio::new(1, 2)), Approx(n) if n.is_nan()));
+        assert_eq!(Approx(4.0).pow_ratio(Ratio::new(3, 2)), Approx(8.0));
+    }
+
+    #[test]
+    fn large_exponents_are_approximated() {
+        assert!(matches!(Number::new(2).powi(MAX_EXACT_EXPONENT), Exact(_)));
+        assert_eq!(Number::new(2).powi(2_000_000_000), Approx(f64::INFINITY));
+        assert_eq!(Number::new(2).powi(-2_000_000_000), Approx(0.0));
+        assert_eq!(Number::one().powi(2_000_000_000), Approx(1.0));
+    }
+}
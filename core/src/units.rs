@@ -1,6 +1,6 @@
-use crate::{number::Number, Error};
-use num::rational::Ratio;
-use std::{cmp, collections::BTreeMap, fmt, ops};
+use crate::{format_unit, number::Number, Environment, Error};
+use num::{rational::Ratio, ToPrimitive, Zero};
+use std::{cmp, collections::BTreeMap, fmt, hash, ops};
 
 #[derive(Clone, Debug)]
 pub struct Quantity {
@@ -56,11 +56,16 @@ impl cmp::PartialOrd for Quantity {
 
 impl fmt::Display for Quantity {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Write the sign separately, so a negative zero from an approximate
+        // calculation is shown as 0 rather than -0
+        let sign = if self.is_negative() { "-" } else { "" };
+        let magnitude = self.number.clone().abs();
+
         let unit_str = self.unit.to_string();
         if unit_str.is_empty() {
-            write!(f, "{}", self.number)
+            write!(f, "{sign}{magnitude}")
         } else {
-            write!(f, "{} {}", self.number, self.unit)
+            write!(f, "{sign}{magnitude} {}", self.unit)
         }
     }
 }
@@ -73,6 +78,86 @@ impl Quantity {
         }
     }
 
+    /// Like [`Quantity::normalize`] but keeps a named unit from the environment
+    /// when one matches the dimension, so that `20 N` stays `20 N` instead of
+    /// being expressed as `20000 gm/s^2`.
+    pub fn normalize_named(&self, env: &Environment) -> Self {
+        let (quantity, _) = format_unit(self.clone(), env);
+        quantity
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.number.signum() < 0
+    }
+
+    pub fn is_positive(&self) -> bool {
+        self.number.signum() > 0
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.number.signum() == 0
+    }
+
+    /// The magnitude of the quantity in its base units as a float, `2 km` is `2000.0`, which
+    /// together with [`Quantity::base_unit_str`] is enough to label the axis of a plot. This is
+    /// lossy for exact numbers that have no exact float representation, like 1/3.
+    pub fn to_f64_in_base(&self) -> f64 {
+        match self.clone().normalize().number {
+            Number::Exact(n) => n.to_f64().unwrap_or(f64::NAN),
+            Number::Approx(n) => n,
+        }
+    }
+
+    /// The base units of the quantity without any scale, `2 km` is in `m`
+    pub fn base_unit_str(&self) -> String {
+        Unit(Number::one(), self.unit.1.clone()).to_string()
+    }
+
+    /// Raise both the number and the unit to an integer power, 3 m squared is 9 m^2
+    pub fn powi(self, exp: i32) -> Self {
+        Quantity {
+            number: self.number.powi(exp),
+            unit: self.unit.powi(exp),
+        }
+    }
+
+    /// Raise both the number and the unit to a rational power, `(9 m^2)^(1/2)` is `3 m`
+    pub fn pow_ratio(self, exp: Ratio<i32>) -> Self {
+        Quantity {
+            number: self.number.pow_ratio(widen(exp)),
+            unit: self.unit.pow_ratio(exp),
+        }
+    }
+
+    /// Raise the quantity to a dimensionless power. Exact ratios are applied to the unit
+    /// as well while approximate exponents are only allowed for dimensionless quantities,
+    /// since there is no sensible unit for something like `m^0.3333333`.
+    pub fn pow(self, exp: Quantity) -> Result<Self, Error> {
+        let exp = exp.normalize();
+        if !exp.unit.1.is_empty() {
+            return Err(Error::InvalidUnitOperation);
+        }
+        if self.number == Number::zero() && exp.number.signum() < 0 {
+            return Err(Error::ZeroToNegativePower);
+        }
+
+        let ratio = match &exp.number {
+            Number::Exact(n) => n.numer().to_i32().zip(n.denom().to_i32()),
+            Number::Approx(_) => None,
+        };
+
+        match ratio {
+            Some((numerator, denominator)) => {
+                Ok(self.pow_ratio(Ratio::new(numerator, denominator)))
+            }
+            None if self.unit.1.is_empty() => Ok(Quantity {
+                number: self.normalize().number.powf(exp.number),
+                unit: Unit::unitless(),
+            }),
+            None => Err(Error::InvalidUnitOperation),
+        }
+    }
+
     pub fn try_convert(&self, target_unit: Unit) -> Option<Self> {
         if self.unit.1 != target_unit.1 {
             None
@@ -85,6 +170,67 @@ impl Quantity {
     }
 }
 
+/// A quantity that implements [`Eq`] and [`hash::Hash`] so that it can be used as the key of
+/// a map, or be deduplicated in a set. It is normalized to its base units, so `1 km` and
+/// `1000 m` are the same key.
+///
+/// Approximate numbers are compared and hashed by their bit pattern. An approximate
+/// quantity is therefore never equal to an exact one, `-0.0` differs from `0.0`, and `NaN`
+/// is equal to itself. Normalizing an approximate quantity may also round it, so
+/// `0.1 km` and `100 m` are not guaranteed to be the same key when both are approximate.
+#[derive(Clone, Debug)]
+pub struct CanonicalQuantity(Quantity);
+
+impl CanonicalQuantity {
+    pub fn new(quantity: Quantity) -> Self {
+        Self(quantity.normalize())
+    }
+
+    /// The quantity expressed in its base units
+    pub fn quantity(&self) -> &Quantity {
+        &self.0
+    }
+
+    pub fn into_inner(self) -> Quantity {
+        self.0
+    }
+}
+
+impl From<Quantity> for CanonicalQuantity {
+    fn from(quantity: Quantity) -> Self {
+        Self::new(quantity)
+    }
+}
+
+impl cmp::PartialEq for CanonicalQuantity {
+    fn eq(&self, other: &Self) -> bool {
+        let same_number = match (&self.0.number, &other.0.number) {
+            (Number::Exact(a), Number::Exact(b)) => a == b,
+            (Number::Approx(a), Number::Approx(b)) => a.to_bits() == b.to_bits(),
+            _ => false,
+        };
+        same_number && self.0.unit.1 == other.0.unit.1
+    }
+}
+
+impl cmp::Eq for CanonicalQuantity {}
+
+impl hash::Hash for CanonicalQuantity {
+    fn hash<H: hash::Hasher>(&self, state: &mut H) {
+        match &self.0.number {
+            Number::Exact(n) => {
+                state.write_u8(0);
+                n.hash(state);
+            }
+            Number::Approx(n) => {
+                state.write_u8(1);
+                n.to_bits().hash(state);
+            }
+        }
+        self.0.unit.1.hash(state);
+    }
+}
+
 impl ops::Add for Quantity {
     type Output = Result<Self, Error>;
 
@@ -198,9 +344,97 @@ impl Unit {
         Self(Number::one(), BTreeMap::new())
     }
 
+    /// A plain number, without any base units and with a scale of one. Dimensionless
+    /// units with another scale, like a percent or a dozen, are not unitless.
+    pub fn is_unitless(&self) -> bool {
+        self.1.is_empty() && self.0 == Number::one()
+    }
+
     pub fn rescaled(self, scale: Number) -> Self {
         Self(self.0 * scale, self.1)
     }
+
+    /// Describe the dimension of the unit using the long names of its base units,
+    /// a newton would for example be "gram·meter/second^2".
+    pub fn dimension(&self) -> String {
+        let describe = |base_units: Vec<(&BaseUnit, Ratio<i32>)>| {
+            base_units
+                .into_iter()
+                .map(|(BaseUnit(long_name, _), exp)| {
+                    if exp == Ratio::new(1, 1) {
+                        long_name.clone()
+                    } else {
+                        format!("{long_name}^{exp}")
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("·")
+        };
+
+        let numerator = describe(
+            self.1
+                .iter()
+                .filter(|(_, exp)| **exp > Ratio::new(0, 1))
+                .map(|(base_unit, exp)| (base_unit, *exp))
+                .collect(),
+        );
+        let denominator = describe(
+            self.1
+                .iter()
+                .filter(|(_, exp)| **exp < Ratio::new(0, 1))
+                .map(|(base_unit, exp)| (base_unit, -exp))
+                .collect(),
+        );
+
+        match (numerator.is_empty(), denominator.is_empty()) {
+            (true, true) => "1".to_string(),
+            (false, true) => numerator,
+            (true, false) => format!("1/{denominator}"),
+            (false, false) => format!("{numerator}/{denominator}"),
+        }
+    }
+
+    /// Raise the unit to an integer power by multiplying the exponents of all base units
+    pub fn powi(self, exp: i32) -> Self {
+        let Unit(scale, base_units) = self;
+        Self(
+            scale.powi(exp),
+            base_units
+                .into_iter()
+                .map(|(base_unit, ratio)| (base_unit, ratio * exp))
+                .filter(|(_, ratio)| !ratio.is_zero())
+                .collect(),
+        )
+    }
+
+    /// The reciprocal unit, which has the inverted scale and all exponents negated
+    pub fn inverse(self) -> Self {
+        let Unit(scale, base_units) = self;
+        Self(
+            Number::one() / scale,
+            base_units
+                .into_iter()
+                .map(|(base_unit, ratio)| (base_unit, -ratio))
+                .collect(),
+        )
+    }
+
+    /// Raise the unit to a rational power, `m^2` to the power of 3/4 is `m^3/2`
+    pub fn pow_ratio(self, exp: Ratio<i32>) -> Self {
+        let Unit(scale, base_units) = self;
+        Self(
+            scale.pow_ratio(widen(exp)),
+            base_units
+                .into_iter()
+                .map(|(base_unit, ratio)| (base_unit, ratio * exp))
+                .filter(|(_, ratio)| *ratio != Ratio::new(0, 1))
+                .collect(),
+        )
+    }
+}
+
+fn widen(ratio: Ratio<i32>) -> Ratio<i64> {
+    Ratio::new(*ratio.numer() as i64, *ratio.denom() as i64)
 }
 
 impl fmt::Display for Unit {
@@ -295,6 +529,8 @@ impl ops::Mul for Unit {
                     + pow2.get(base).unwrap_or(&Ratio::new(0i32, 1i32));
                 (base.clone(), exp)
             })
+            // Base units that cancel out should not be a part of the unit
+            .filter(|(_, exp)| !exp.is_zero())
             .collect();
 
         Self(scale_res, powers_res)
@@ -318,6 +554,8 @@ impl ops::Div for Unit {
                     - pow2.get(base).unwrap_or(&Ratio::new(0i32, 1i32));
                 (base.clone(), exp)
             })
+            // Base units that cancel out should not be a part of the unit
+            .filter(|(_, exp)| !exp.is_zero())
             .collect();
 
         Self(scale_res, powers_res)
@@ -327,6 +565,7 @@ impl ops::Div for Unit {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{eval, parse};
     use lazy_static::lazy_static;
     use std::collections::HashMap;
 
@@ -433,4 +672,196 @@ mod tests {
 
         assert_eq!(result.unwrap().to_string(), "20625 gm/s^2");
     }
+
+    #[test]
+    fn cancelled_base_units() {
+        let meters = Quantity {
+            number: Number::new(6),
+            unit: unit('m'),
+        };
+        let seconds = Quantity {
+            number: Number::new(2),
+            unit: unit('s'),
+        };
+
+        assert_eq!(
+            (meters.clone() / seconds.clone() * seconds).to_string(),
+            "6 m"
+        );
+        assert!((meters.clone() / meters.clone()).unit.is_unitless());
+        assert!(meters.powi(0).unit.is_unitless());
+    }
+
+    #[test]
+    fn powers() {
+        let three_meters = Quantity {
+            number: Number::new(3),
+            unit: unit('m'),
+        };
+        let two_kilometers = Quantity {
+            number: Number::new(2),
+            unit: unit('m').rescaled(Number::new(1000)),
+        };
+
+        assert_eq!(three_meters.clone().powi(2).to_string(), "9 m^2");
+        assert_eq!(three_meters.clone().powi(3).to_string(), "27 m^3");
+        assert_eq!(
+            three_meters.clone().powi(2),
+            three_meters.clone() * three_meters.clone()
+        );
+        assert_eq!(
+            two_kilometers.powi(2).normalize().to_string(),
+            "4000000 m^2"
+        );
+        assert_eq!(three_meters.powi(-1).to_string(), "1/3 1/m");
+    }
+
+    #[test]
+    fn signs() {
+        let quantity = |number| Quantity {
+            number,
+            unit: unit('m'),
+        };
+
+        assert!(quantity(Number::new(-2)).is_negative());
+        assert!(quantity(Number::new(2)).is_positive());
+        assert!(quantity(Number::zero()).is_zero());
+        assert!(quantity(Number::Approx(-0.1)).is_negative());
+        assert!(quantity(Number::Approx(0.1)).is_positive());
+
+        let negative_zero = quantity(Number::Approx(-0.0));
+        assert!(negative_zero.is_zero() && !negative_zero.is_negative());
+        assert_eq!(negative_zero.to_string(), "0 m");
+        assert_eq!(quantity(Number::Approx(-1.5)).to_string(), "-1.5 m");
+    }
+
+    #[test]
+    fn canonical_quantities() {
+        use std::collections::HashSet;
+
+        let quantity = |number, scale| Quantity {
+            number,
+            unit: unit('m').rescaled(scale),
+        };
+        let one_km = quantity(Number::new(1), Number::new(1000));
+        let thousand_m = quantity(Number::new(1000), Number::one());
+        let approx_km = quantity(Number::Approx(1.0), Number::new(1000));
+
+        let keys: HashSet<CanonicalQuantity> = [one_km.clone(), thousand_m, approx_km.clone()]
+            .into_iter()
+            .map(CanonicalQuantity::from)
+            .collect();
+        assert_eq!(keys.len(), 2);
+        assert!(keys.contains(&CanonicalQuantity::new(one_km)));
+        assert_eq!(
+            CanonicalQuantity::new(approx_km).into_inner().to_string(),
+            "1000 m"
+        );
+
+        // Approximate numbers are compared by their bits
+        let nan = CanonicalQuantity::new(quantity(Number::Approx(f64::NAN), Number::one()));
+        assert_eq!(nan, nan.clone());
+        assert_ne!(
+            CanonicalQuantity::new(quantity(Number::Approx(0.0), Number::one())),
+            CanonicalQuantity::new(quantity(Number::Approx(-0.0), Number::one()))
+        );
+
+        // The same number of another dimension is another key
+        let thousand_s = Quantity {
+            number: Number::new(1000),
+            unit: unit('s'),
+        };
+        assert!(!keys.contains(&CanonicalQuantity::new(thousand_s)));
+    }
+
+    #[test]
+    fn to_f64_in_base() {
+        let distance = Quantity {
+            number: Number::new(5) / Number::new(2),
+            unit: unit('m').rescaled(Number::new(1000)),
+        };
+        assert_eq!(distance.to_f64_in_base(), 2500.0);
+        assert_eq!(distance.base_unit_str(), "m");
+
+        let third = Quantity {
+            number: Number::one() / Number::new(3),
+            unit: unit('m') / unit('s'),
+        };
+        assert_eq!(third.to_f64_in_base(), 1.0 / 3.0);
+        assert_eq!(third.base_unit_str(), "m/s");
+    }
+
+    #[test]
+    fn inverse() {
+        let hertz = unit('s').inverse();
+        assert_eq!(hertz.to_string(), "1/s");
+        assert_eq!(hertz.clone().inverse(), unit('s'));
+        assert_eq!(hertz, unit('s').powi(-1));
+
+        let per_kilometer = unit('m').rescaled(Number::new(1000)).inverse();
+        assert_eq!(per_kilometer.0, Number::one() / Number::new(1000));
+    }
+
+    #[test]
+    fn rational_powers() {
+        let quantity = |number: i64, unit| Quantity {
+            number: Number::new(number),
+            unit,
+        };
+        let area = quantity(16, unit('m').powi(2));
+
+        assert_eq!(
+            area.clone().pow_ratio(Ratio::new(3, 4)).to_string(),
+            "8 m^3/2"
+        );
+        assert_eq!(
+            area.clone().pow(quantity(1, Unit::unitless())).unwrap(),
+            area
+        );
+        assert_eq!(
+            area.clone().pow(quantity(0, Unit::unitless())).unwrap(),
+            quantity(1, Unit::unitless())
+        );
+        assert!(area.clone().pow(quantity(2, unit('m'))).is_err());
+
+        // Approximate exponents can't be used with units
+        let approx = Quantity {
+            number: Number::Approx(0.5),
+            unit: Unit::unitless(),
+        };
+        assert!(area.pow(approx.clone()).is_err());
+        assert_eq!(
+            quantity(4, Unit::unitless()).pow(approx).unwrap().number,
+            Number::Approx(2.0)
+        );
+    }
+
+    #[test]
+    fn dimension() {
+        assert_eq!(unit('N').dimension(), "gram·meter/second^2");
+        assert_eq!(unit('m').dimension(), "meter");
+        assert_eq!(unit('0').dimension(), "1");
+        assert_eq!((unit('0') / unit('s')).dimension(), "1/second");
+        assert_eq!((unit('m') * unit('s') / unit('s')).dimension(), "meter");
+    }
+
+    #[test]
+    fn normalize_named() {
+        let mut env = Environment::default();
+        let source = "unit newton N = 1000 g * m / s / s\n20 N";
+        let value = eval(&parse(source).unwrap(), &mut env).unwrap();
+        let force = value.quantity().unwrap();
+
+        let named = force.normalize_named(&env);
+        assert_eq!(named.number, Number::new(20));
+        assert_eq!(
+            env.unit_name(&named.unit),
+            Some(("newton".to_string(), Some("N".to_string())))
+        );
+
+        // Without a named unit we fall back to the base units
+        let unnamed = (force.clone() * force).normalize_named(&env);
+        assert_eq!(unnamed.number, Number::new(400_000_000));
+        assert_eq!(env.unit_name(&unnamed.unit), None);
+    }
 }
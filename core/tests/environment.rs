@@ -0,0 +1,389 @@
+use hypatia_lib::{
+    eval, format_engineering, format_unit,
+    number::Number,
+    parse, report_error,
+    units::{Quantity, Unit},
+    Environment, Error, TieBreak, Value,
+};
+
+fn run(source: &str, env: &mut Environment) {
+    value(source, env).expect("Failed to evaluate the expression");
+}
+
+fn value(source: &str, env: &mut Environment) -> Result<Value, Error> {
+    let ast = parse(source).expect("Failed to parse the source text");
+    eval(&ast, env)
+}
+
+fn quantity(source: &str, env: &mut Environment) -> Quantity {
+    value(source, env)
+        .and_then(|value| value.quantity())
+        .expect("Failed to evaluate the expression to a quantity")
+}
+
+#[test]
+fn builtin_names() {
+    let mut env = Environment::default();
+    run("unit furlong = 201 m\nprefix giga G = 1000000000\nx = 2\ncount = 3", &mut env);
+
+    assert!(env.is_builtin("meter"));
+    assert!(env.is_builtin("m"));
+    assert!(env.is_builtin("kilo"));
+
+    assert!(!env.is_builtin("furlong"));
+    assert!(!env.is_builtin("giga"));
+    assert!(!env.is_builtin("x"));
+    assert!(!env.is_builtin("undeclared"));
+    // Shadowed by the user
+    assert!(!env.is_builtin("count"));
+
+    let names = env.builtin_names();
+    assert!(names.contains(&"second".to_string()));
+    assert!(names.contains(&"k".to_string()));
+    assert!(!names.contains(&"furlong".to_string()));
+    assert!(!names.contains(&"x".to_string()));
+
+    assert!(Environment::without_prelude().builtin_names().is_empty());
+}
+
+#[test]
+fn preferred_unit() {
+    let mut env = Environment::default();
+    run("unit newton N = 1000 g * m / s / s", &mut env);
+
+    let (force, (long_name, _)) = format_unit(quantity("2 N", &mut env), &env);
+    assert_eq!(long_name, "newton");
+    assert_eq!(force.number, Number::new(2));
+
+    run("unit preferred dyne dyn = 1 g * cm / s / s", &mut env);
+
+    let (force, (long_name, short_name)) = format_unit(quantity("2 N", &mut env), &env);
+    assert_eq!(long_name, "dyne");
+    assert_eq!(short_name, Some("dyn".to_string()));
+    assert_eq!(force.number, Number::new(200_000));
+}
+
+#[test]
+fn prelude_source() {
+    let source = Environment::prelude_source();
+    assert!(source.contains("unit meter m"));
+
+    // A customized prelude can be evaluated in an environment without the prelude
+    let mut env = Environment::without_prelude();
+    run(&format!("{source}\nunit foot ft = 0.3048 m"), &mut env);
+    run("x = 3 ft + 2 km", &mut env);
+}
+
+#[test]
+fn unit_alias() {
+    let mut env = Environment::default();
+    run("unit klick = km\nunit metre = m", &mut env);
+
+    let klick = quantity("1 klick", &mut env);
+    assert_eq!(klick, quantity("1 km", &mut env));
+    assert_eq!(klick.unit.0, Number::new(1000));
+    assert_eq!(env.unit_name(&klick.unit), Some(("klick".to_string(), None)));
+
+    // The original unit is still used to display quantities of the same scale
+    let (meter, (long_name, _)) = format_unit(quantity("5 metre", &mut env), &env);
+    assert_eq!(long_name, "meter");
+    assert_eq!(meter.number, Number::new(5));
+}
+
+#[test]
+fn prefix_forms() {
+    let mut env = Environment::default();
+    run(
+        "prefix quux = 10\nprefix _ Q = 100\nprefix zork Z = 1000",
+        &mut env,
+    );
+
+    // Long names only go together with long names and short with short
+    assert_eq!(
+        quantity("1 quuxmeter", &mut env),
+        quantity("10 m", &mut env)
+    );
+    assert!(value("1 quuxm", &mut env).is_err());
+    assert_eq!(quantity("1 Qm", &mut env), quantity("100 m", &mut env));
+    assert!(value("1 Qmeter", &mut env).is_err());
+    assert_eq!(
+        quantity("1 zorkmeter", &mut env),
+        quantity("1000 m", &mut env)
+    );
+    assert_eq!(quantity("1 Zm", &mut env), quantity("1000 m", &mut env));
+
+    assert!(value("prefix _ = 5", &mut env).is_err());
+}
+
+#[test]
+fn multiple_prefixes() {
+    let mut env = Environment::default();
+    run(
+        "prefix mega M = 1e6, giga G = 1e9,\n    _ T = 1e12 // tera\nx = 1",
+        &mut env,
+    );
+
+    assert_eq!(quantity("1 Mm", &mut env), quantity("1000000 m", &mut env));
+    assert_eq!(
+        quantity("1 gigameter", &mut env),
+        quantity("1000 Mm", &mut env)
+    );
+    assert_eq!(quantity("1 Tm", &mut env), quantity("1000 Gm", &mut env));
+    assert_eq!(quantity("x", &mut env), quantity("1", &mut env));
+
+    // The prefixes before an invalid one are still declared
+    assert!(value("prefix peta P = 1e15, _ = 5", &mut env).is_err());
+    assert_eq!(quantity("1 Pm", &mut env), quantity("1000 Tm", &mut env));
+}
+
+#[test]
+fn register_native() {
+    let mut env = Environment::default();
+    env.register_native("usd_to_sek", 1, |arguments| {
+        let usd = arguments[0].expect_quantity("usd_to_sek")?;
+        Ok(Value::Quantity(Quantity {
+            number: usd.number * Number::new(10),
+            unit: usd.unit,
+        }))
+    })
+    .unwrap();
+
+    assert_eq!(
+        value("f(x) = usd_to_sek(x) + 1\nf(3)", &mut env)
+            .unwrap()
+            .to_string(),
+        "31"
+    );
+    assert!(value("usd_to_sek(1, 2)", &mut env).is_err());
+    assert!(value("usd_to_sek(true)", &mut env).is_err());
+
+    // The name can't be taken twice
+    assert!(env
+        .register_native("usd_to_sek", 1, |_| Ok(Value::Nothing))
+        .is_err());
+}
+
+#[test]
+fn dimensionless_named_unit() {
+    let mut env = Environment::default();
+    run("unit quarter = 0.25\nunit dozen = 12", &mut env);
+
+    let cases = [
+        ("0.25 in quarter", Number::new(1), "quarter"),
+        ("36 in dozen", Number::new(3), "dozen"),
+        ("2 dozen", Number::new(2), "dozen"),
+    ];
+    for (source, number, unit) in cases {
+        let (named, (long_name, _)) = format_unit(quantity(source, &mut env), &env);
+        assert_eq!(
+            (named.number, long_name.as_str()),
+            (number, unit),
+            "{source}"
+        );
+    }
+}
+
+#[test]
+fn commensurable_units() {
+    let mut env = Environment::without_prelude();
+    run(
+        "unit meter m
+        unit second s
+        unit mile mi = 1609.344 m
+        unit kilometer km = 1000 m
+        unit centimeter cm = 0.01 m
+        unit hertz = 1 / s",
+        &mut env,
+    );
+
+    let length = quantity("5 m", &mut env);
+    let units = env.commensurable_units(&length);
+    let names: Vec<_> = units
+        .iter()
+        .map(|((long_name, short_name), _)| (long_name.as_str(), short_name.as_deref()))
+        .collect();
+    assert_eq!(
+        names,
+        [
+            ("centimeter", Some("cm")),
+            ("meter", Some("m")),
+            ("kilometer", Some("km")),
+            ("mile", Some("mi")),
+        ]
+    );
+    let (_, Unit(scale, _)) = &units[2];
+    assert_eq!(*scale, Number::new(1000));
+}
+
+#[test]
+fn dimensionless_display() {
+    let mut env = Environment::default();
+    run(
+        "unit one = 1\nunit preferred percent = 0.01\nunit dozen = 12",
+        &mut env,
+    );
+
+    let cases = [
+        // A plain number has no unit, even though "one" has the same scale
+        ("5", "5", ""),
+        ("50 percent", "50", "percent"),
+        ("0.5 in percent", "50", "percent"),
+        // The preferred percent is only used for quantities with the same scale
+        ("2 dozen", "2", "dozen"),
+        ("2 dozen * 2 percent", "12/25", ""),
+    ];
+    for (source, number, unit) in cases {
+        let (named, (long_name, _)) = format_unit(quantity(source, &mut env), &env);
+        assert_eq!(
+            (named.number.to_string().as_str(), long_name.as_str()),
+            (number, unit),
+            "{source}"
+        );
+    }
+}
+
+#[test]
+fn tie_break() {
+    let mut env = Environment::without_prelude();
+    run(
+        "unit meter\nunit metre m = meter\nunit mtr = meter",
+        &mut env,
+    );
+    let length = quantity("5 meter", &mut env);
+
+    let mut unit_name = |tie_break: TieBreak| {
+        env.set_tie_break(tie_break);
+        let (_, (long_name, _)) = format_unit(length.clone(), &env);
+        long_name
+    };
+
+    assert_eq!(unit_name(TieBreak::FirstDeclared), "meter");
+    assert_eq!(unit_name(TieBreak::LastDeclared), "mtr");
+    assert_eq!(unit_name(TieBreak::ShortName), "metre");
+    assert_eq!(
+        unit_name(TieBreak::Ranking(vec!["mtr".into(), "metre".into()])),
+        "mtr"
+    );
+    assert_eq!(unit_name(TieBreak::Ranking(vec!["foot".into()])), "meter");
+}
+
+#[test]
+fn engineering_notation() {
+    let mut env = Environment::default();
+    run("unit inch = 0.0254 m", &mut env);
+
+    let cases = [
+        ("1500 m", "1.5 km"),
+        ("15 m", "15 m"),
+        ("0.0015 m", "1.5 mm"),
+        ("0.015 g", "15 mg"),
+        // There is no prefix for a million in the prelude
+        ("1.5e6 m", "1.5 × 10^6 m"),
+        ("2.5e-7 m", "250 × 10^-9 m"),
+        // Units without a short name use the long names of prefixes
+        ("3000 inch", "3 kiloinch"),
+        ("12000", "12 × 10^3"),
+        ("1500 m / s", "1.5 × 10^3 m/s"),
+    ];
+    for (source, expected) in cases {
+        assert_eq!(
+            format_engineering(quantity(source, &mut env), &env),
+            expected
+        );
+    }
+}
+
+#[test]
+fn parse_unit() {
+    let mut env = Environment::default();
+    run("unit hour h = 3600 s\nx = 2 m", &mut env);
+
+    let unit = |source: &str, env: &Environment| {
+        env.parse_unit(source)
+            .map(|unit| unit.to_string())
+            .map_err(|error| report_error(error, source))
+    };
+
+    assert_eq!(
+        env.parse_unit("km/h").unwrap(),
+        quantity("1 km / 1 h", &mut env).unit
+    );
+    assert_eq!(
+        env.parse_unit("kg m/s^2").unwrap(),
+        quantity("1 kg * 1 m / 1 s^2", &mut env).unit
+    );
+    assert_eq!(
+        env.parse_unit("1000 m").unwrap(),
+        quantity("1 m", &mut env).unit
+    );
+    assert_eq!(
+        unit("(m/s)^(1/2)", &env).unwrap(),
+        unit("m^(1/2) s^-1/2", &env).unwrap()
+    );
+
+    // Only units are allowed, not variables or any other expressions
+    assert!(unit("x", &env).unwrap_err().contains("x"));
+    assert!(env.parse_unit("furlong").is_err());
+    assert!(unit("m + m", &env).unwrap_err().contains("Not a unit"));
+    assert!(matches!(env.parse_unit("y = 2 m"), Err(Error::NotAUnit(_))));
+    assert!(matches!(env.parse_unit("m\ns"), Err(Error::NotAUnit(_))));
+    assert!(env.parse_unit("km/").is_err());
+}
+
+#[test]
+fn callable_function() {
+    let mut env = Environment::default();
+    run(
+        "k = 10\nf(x) = k * x + 1 m\nsum(first, rest...) = first + count(rest)",
+        &mut env,
+    );
+
+    let f = value("f", &mut env).unwrap().as_callable().unwrap();
+    let three_meters = Value::Quantity(quantity("3 m", &mut env));
+    assert_eq!(
+        f(&[three_meters]).unwrap(),
+        Value::Quantity(quantity("31 m", &mut env))
+    );
+    assert!(f(&[]).is_err());
+
+    // Rest parameters and native functions work just like in a call expression
+    let sum = value("sum", &mut env).unwrap().as_callable().unwrap();
+    let arguments = [
+        value("1", &mut env).unwrap(),
+        Value::Bool(true),
+        Value::Nothing,
+    ];
+    assert_eq!(sum(&arguments).unwrap(), value("3", &mut env).unwrap());
+
+    let count = value("count", &mut env).unwrap().as_callable().unwrap();
+    assert_eq!(
+        count(&[Value::List(vec![])]).unwrap(),
+        value("0", &mut env).unwrap()
+    );
+
+    assert!(value("2 m", &mut env).unwrap().as_callable().is_err());
+}
+
+#[test]
+fn snapshot() {
+    let mut env = Environment::default();
+    run("x = 1\nunit foo = 2 m", &mut env);
+
+    let mut snapshot = env.snapshot();
+    let mut clone = env.clone();
+    run(
+        "update x = 2\ny = 3\nunit bar = 3 m\nprefix zap z = 7",
+        &mut snapshot,
+    );
+
+    // Nothing done to the snapshot is visible through the original
+    assert_eq!(value("x == 1", &mut env).unwrap(), Value::Bool(true));
+    assert!(value("y", &mut env).is_err());
+    assert!(value("1 bar", &mut env).is_err());
+    assert!(value("1 zfoo", &mut env).is_err());
+    assert!(value("1 zbar", &mut env).is_err());
+
+    // While a plain clone shares everything with the original
+    run("z = 4", &mut clone);
+    assert!(value("z", &mut env).is_ok());
+}
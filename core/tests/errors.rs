@@ -0,0 +1,141 @@
+use hypatia_lib::{eval, parse, report_error, Environment, Error};
+
+#[test]
+fn unknown_unit_has_span() {
+    let source = "x = 2 m\ny = 5 furlong";
+    let ast = parse(source).expect("Failed to parse the source text");
+    let mut env = Environment::default();
+
+    let Err(Error::UnknownUnit(name, span)) = eval(&ast, &mut env) else {
+        panic!("Expected an unknown unit error");
+    };
+    assert_eq!(name, "furlong");
+    assert_eq!(&source[span.clone()], "furlong");
+
+    let report = report_error(Error::UnknownUnit(name, span), source);
+    assert!(report.contains("furlong"));
+}
+
+#[test]
+fn native_type_error_names_function() {
+    let source = "hex(true)";
+    let ast = parse(source).expect("Failed to parse the source text");
+    let mut env = Environment::default();
+
+    let error = eval(&ast, &mut env).expect_err("Expected a type error");
+    assert_eq!(
+        report_error(error, source),
+        "hex expected a quantity but got a value of type bool."
+    );
+
+    // A quantity of the wrong kind says what is wrong with it
+    let mut report = |source: &str| {
+        let error = eval(&parse(source).unwrap(), &mut env).expect_err("Expected an error");
+        report_error(error, source)
+    };
+    assert_eq!(
+        report("hex(3 m)"),
+        "hex expected a number without a unit but got 3 m."
+    );
+    assert_eq!(report("hex(5/2)"), "hex expected an integer but got 5/2.");
+}
+
+#[test]
+fn call_errors() {
+    let mut env = Environment::default();
+    let mut report = |source: &str| {
+        let error = eval(&parse(source).unwrap(), &mut env).expect_err("Expected an error");
+        report_error(error, source)
+    };
+
+    assert_eq!(
+        report("x = 2\nx(1)"),
+        "Expected a function but got a value of type quantity."
+    );
+    assert_eq!(
+        report("f(x) = x\nf(1, 2)"),
+        "The function takes 1 argument but was given 2."
+    );
+    assert_eq!(
+        report("first(x, y, rest...) = x\nfirst(1)"),
+        "The function takes at least 2 arguments but was given 1."
+    );
+    assert_eq!(
+        report("hex()"),
+        "hex takes 1 to 2 arguments but was given 0."
+    );
+}
+
+#[test]
+fn power_errors() {
+    let mut env = Environment::default();
+    let mut run = |source: &str| eval(&parse(source).unwrap(), &mut env);
+
+    assert!(matches!(run("0^-1"), Err(Error::ZeroToNegativePower)));
+    assert!(matches!(
+        run("(0 m)^(-1/2)"),
+        Err(Error::ZeroToNegativePower)
+    ));
+    assert!(run("0^0").is_ok());
+
+    // Huge exponents are approximated rather than computed exactly
+    assert_eq!(run("2^2000000000").unwrap().to_string(), "inf");
+    assert_eq!(run("2^-2000000000").unwrap().to_string(), "0");
+}
+
+#[test]
+fn strict_if_branches() {
+    let run = |source: &str, strict: bool| {
+        let mut env = Environment::default();
+        env.set_strict(strict);
+        eval(&parse(source).unwrap(), &mut env)
+    };
+
+    let mismatched = "if 1 < 2 { 1 m } else { true }";
+    assert!(run(mismatched, false).is_ok());
+    assert!(matches!(
+        run(mismatched, true),
+        Err(Error::TypeMismatch("quantity", "bool"))
+    ));
+    assert!(matches!(
+        run("if false { 2 > 1 } else if true { false } else { 3 }", true),
+        Err(Error::TypeMismatch("bool", "quantity"))
+    ));
+
+    // Branches of the same kind, giving nothing or depending on variables are fine
+    assert!(run("if 1 < 2 { 1 m } else { 2 km + 3 m }", true).is_ok());
+    assert!(run("if true { 1 m }", true).is_ok());
+    assert!(run("x = true\nif true { 1 m } else { x }", true).is_ok());
+}
+
+#[test]
+fn failed_assertion() {
+    let source = "x = 2 m\nassert x > 3 m";
+    let ast = parse(source).expect("Failed to parse the source text");
+    let mut env = Environment::default();
+
+    let Err(Error::AssertionFailed(span)) = eval(&ast, &mut env) else {
+        panic!("Expected a failed assertion");
+    };
+    assert_eq!(&source[span.clone()], "x > 3 m");
+    assert!(report_error(Error::AssertionFailed(span), source).contains("x > 3 m"));
+
+    let ast = parse("assert 2 m").unwrap();
+    assert!(matches!(eval(&ast, &mut env), Err(Error::InvalidType)));
+}
+
+#[test]
+fn error_spans() {
+    let source = "x = (1 + 2";
+    let errors = parse(source).expect_err("Expected a parsing error");
+    assert!(errors.iter().all(|error| error.span().is_some()));
+
+    let mut env = Environment::default();
+    let mut span_of = |source: &str| {
+        let error = eval(&parse(source).unwrap(), &mut env).expect_err("Expected an error");
+        error.span().map(|span| source[span].to_string())
+    };
+    assert_eq!(span_of("2 + 5 furlong").as_deref(), Some("furlong"));
+    assert_eq!(span_of("assert 1 > 2").as_deref(), Some("1 > 2"));
+    assert_eq!(span_of("unknown"), None);
+}
@@ -0,0 +1,46 @@
+use hypatia_lib::{eval_all, eval_program_with_observer, parse, Environment, Error};
+
+#[test]
+fn observe_each_statement() {
+    let source = "x = 2\ny = x * 3\nz = w\nnever = 1";
+    let ast = parse(source).expect("Failed to parse the source text");
+    let mut env = Environment::default();
+
+    let mut observed = Vec::new();
+    let result = eval_program_with_observer(&ast, &mut env, |(_, span), result| {
+        observed.push((
+            source[span.clone()].to_string(),
+            result.as_ref().map(|value| value.to_string()).ok(),
+        ));
+    });
+
+    assert!(matches!(result, Err(Error::UnknownName(name)) if name == "w"));
+    assert_eq!(
+        observed,
+        vec![
+            ("x = 2".to_string(), Some("2".to_string())),
+            ("y = x * 3".to_string(), Some("6".to_string())),
+            ("z = w".to_string(), None),
+        ]
+    );
+}
+
+#[test]
+fn eval_all_statements() {
+    let source = "x = 2\ny = w\nx * 3\ny + 1\nunit furlong = 201 m";
+    let ast = parse(source).expect("Failed to parse the source text");
+    let mut env = Environment::default();
+
+    let results: Vec<_> = eval_all(&ast, &mut env)
+        .into_iter()
+        .map(|result| result.map(|value| value.to_string()))
+        .collect();
+
+    assert_eq!(results.len(), 5);
+    assert_eq!(results[0].as_deref().ok(), Some("2"));
+    assert!(matches!(&results[1], Err(Error::UnknownName(name)) if name == "w"));
+    // The evaluation continues after the error
+    assert_eq!(results[2].as_deref().ok(), Some("6"));
+    assert!(matches!(&results[3], Err(Error::UnknownName(name)) if name == "y"));
+    assert_eq!(results[4].as_deref().ok(), Some("nothing"));
+}
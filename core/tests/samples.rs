@@ -1,4 +1,4 @@
-use hypatia_lib::{eval, parse, Environment};
+use hypatia_lib::{eval, parse, report_error, Environment};
 use std::{fs, path::Path};
 
 // TODO: Add support for testing error outputs as well
@@ -9,7 +9,13 @@ fn run_test_file(source_file: &Path) {
 
     let ast = parse(source).expect("Failed to parse the source text");
     let mut env = Environment::default();
-    let value = eval(&ast, &mut env).expect("Failed to evaluate the expression");
+    // Show the report, so that it is clear which assert in the sample failed
+    let value = eval(&ast, &mut env).unwrap_or_else(|error| {
+        panic!(
+            "Failed to evaluate the expression: {}",
+            report_error(error, source)
+        )
+    });
     assert_eq!(result.trim(), &format!("{value}"));
 }
 
@@ -117,3 +123,83 @@ fn comparison() {
 fn logical_operators() {
     run_test_file(Path::new("./samples/logical_operators.hyp"));
 }
+
+#[test]
+fn rest_parameter() {
+    run_test_file(Path::new("./samples/rest_parameter.hyp"));
+}
+
+#[test]
+fn count() {
+    run_test_file(Path::new("./samples/count.hyp"));
+}
+
+#[test]
+fn radix() {
+    run_test_file(Path::new("./samples/radix.hyp"));
+}
+
+#[test]
+fn exact() {
+    run_test_file(Path::new("./samples/exact.hyp"));
+}
+
+#[test]
+fn convert() {
+    run_test_file(Path::new("./samples/convert.hyp"));
+}
+
+#[test]
+fn square_cube() {
+    run_test_file(Path::new("./samples/square_cube.hyp"));
+}
+
+#[test]
+fn block_result() {
+    run_test_file(Path::new("./samples/block_result.hyp"));
+}
+
+#[test]
+fn dimension() {
+    run_test_file(Path::new("./samples/dimension.hyp"));
+}
+
+#[test]
+fn range() {
+    run_test_file(Path::new("./samples/range.hyp"));
+}
+
+#[test]
+fn power() {
+    run_test_file(Path::new("./samples/power.hyp"));
+}
+
+#[test]
+fn reciprocal() {
+    run_test_file(Path::new("./samples/reciprocal.hyp"));
+}
+
+#[test]
+fn unit_suffix() {
+    run_test_file(Path::new("./samples/unit_suffix.hyp"));
+}
+
+#[test]
+fn knot() {
+    run_test_file(Path::new("./samples/knot.hyp"));
+}
+
+#[test]
+fn assert() {
+    run_test_file(Path::new("./samples/assert.hyp"));
+}
+
+#[test]
+fn precedence() {
+    run_test_file(Path::new("./samples/precedence.hyp"));
+}
+
+#[test]
+fn numeric() {
+    run_test_file(Path::new("./samples/numeric.hyp"));
+}
@@ -11,12 +11,26 @@ pub enum Expr {
     Program(Vec<Spanned<Self>>),
     Conversion(Box<Spanned<Self>>, Box<Spanned<Self>>),
     BinOp(BinOp, Box<Spanned<Self>>, Box<Spanned<Self>>),
-    FunctionDecl(String, Vec<String>, Box<Spanned<Self>>),
-    FunctionUpdate(String, Vec<String>, Box<Spanned<Self>>),
-    BaseUnitDecl(String, Option<String>),
-    DerivedUnitDecl(String, Option<String>, Box<Spanned<Self>>),
-    PrefixDecl(String, Option<String>, Box<Spanned<Self>>),
+    FunctionDecl(String, Parameters, Box<Spanned<Self>>),
+    FunctionUpdate(String, Parameters, Box<Spanned<Self>>),
+    // The boolean is set for units declared as the preferred unit to display their dimension in
+    BaseUnitDecl(String, Option<String>, bool),
+    DerivedUnitDecl(String, Option<String>, Box<Spanned<Self>>, bool),
+    // A prefix has a long name, a short name or both
+    PrefixDecl(Option<String>, Option<String>, Box<Spanned<Self>>),
+    // Several prefix declarations in one statement, prefix kilo k = 1000, mega M = 1e6
+    PrefixDecls(Vec<Spanned<Self>>),
     UnaryOp(UnaryOp, Box<Spanned<Expr>>),
+    // A condition which must evaluate to true, `assert x > 2 m`
+    Assert(Box<Spanned<Self>>),
+}
+
+/// The parameters of a function declaration, for example `f(x, y, rest...)`.
+/// The optional rest parameter collects any extra arguments into a list.
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Parameters {
+    pub names: Vec<String>,
+    pub rest: Option<String>,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -40,13 +54,16 @@ pub enum BinOp {
     And,
     Or,
     Xor,
+    Pow,
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum Literal {
     Nothing,
     Bool(bool),
-    Quantity(NumberLiteral, Option<String>),
+    Quantity(NumberLiteral, Option<Spanned<String>>),
+    /// A numerator and (non-zero) denominator, only written as an exponent like `5 m^3/4` or `x^(3/4)`
+    Rational(i32, i32),
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -21,8 +21,9 @@ pub fn parse(source: &str) -> Result<Spanned<Expr>, Vec<Simple<String>>> {
 
     // Parse the stream of tokens
     let len = source.chars().count();
+    let tokens = join_continued_lines(tokens.unwrap());
     let (ast, parsing_errors) =
-        parser().parse_recovery(Stream::from_iter(len..len + 1, tokens.unwrap().into_iter()));
+        parser().parse_recovery(Stream::from_iter(len..len + 1, tokens.into_iter()));
 
     // If there are errors, return them
     if parsing_errors.len() + lexing_errors.len() > 0 {
@@ -58,6 +59,7 @@ enum Token {
     Sub,
     Mul,
     Div,
+    Pow,
     Assignment,
     Equal,
     NotEqual,
@@ -73,6 +75,7 @@ enum Token {
     RCurly,
     Semicolon,
     Comma,
+    Ellipsis,
     Newline,
     Comment,
     Prefix,
@@ -83,6 +86,29 @@ enum Token {
     In,
 }
 
+impl Token {
+    fn is_binary_operator(&self) -> bool {
+        matches!(
+            self,
+            Token::Add
+                | Token::Sub
+                | Token::Mul
+                | Token::Div
+                | Token::Pow
+                | Token::Equal
+                | Token::NotEqual
+                | Token::Lt
+                | Token::Gt
+                | Token::Gte
+                | Token::Lte
+                | Token::And
+                | Token::Xor
+                | Token::Or
+                | Token::In
+        )
+    }
+}
+
 impl fmt::Display for Token {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -107,6 +133,7 @@ impl fmt::Display for Token {
             Token::Sub => write!(f, "-"),
             Token::Mul => write!(f, "*"),
             Token::Div => write!(f, "/"),
+            Token::Pow => write!(f, "^"),
             Token::Assignment => write!(f, "="),
             Token::Equal => write!(f, "=="),
             Token::NotEqual => write!(f, "!="),
@@ -122,6 +149,7 @@ impl fmt::Display for Token {
             Token::RCurly => write!(f, "}}"),
             Token::Semicolon => write!(f, ";"),
             Token::Comma => write!(f, ","),
+            Token::Ellipsis => write!(f, "..."),
             Token::Newline => writeln!(f),
             Token::Comment => write!(f, ""),
             Token::Not => write!(f, "not"),
@@ -134,6 +162,33 @@ impl fmt::Display for Token {
     }
 }
 
+/// Remove the line breaks (and comments) directly after a binary operator, since
+/// the expression continues on the next line. So "1 +\n2" is one statement, not two.
+fn join_continued_lines(tokens: Vec<Spanned<Token>>) -> Vec<Spanned<Token>> {
+    let mut result: Vec<Spanned<Token>> = Vec::with_capacity(tokens.len());
+    for (token, span) in tokens {
+        let is_continued = matches!(token, Token::Newline | Token::Comment)
+            && matches!(result.last(), Some((last, _)) if last.is_binary_operator());
+
+        if !is_continued {
+            result.push((token, span));
+        }
+    }
+    result
+}
+
+/// Whether the source ends with a binary operator (ignoring comments and line breaks after
+/// it), which means that the expression continues on the next line, see [`join_continued_lines`]
+pub fn ends_with_operator(source: &str) -> bool {
+    let (tokens, _) = lexer().parse_recovery(source);
+    let last = tokens
+        .unwrap_or_default()
+        .into_iter()
+        .rev()
+        .find(|(token, _)| !matches!(token, Token::Newline | Token::Comment));
+    matches!(last, Some((token, _)) if token.is_binary_operator())
+}
+
 fn ident() -> impl Parser<char, Vec<char>, Error = Simple<char>> + Copy + Clone {
     filter(|c: &char| c.is_alphabetic() || *c == '_')
         .map(Some)
@@ -179,6 +234,7 @@ fn lexer() -> impl Parser<char, Vec<Spanned<Token>>, Error = Simple<char>> {
         '-' => Token::Sub,
         '*' => Token::Mul,
         '/' => Token::Div,
+        '^' => Token::Pow,
         '<' => Token::Lt,
         '>' => Token::Gt,
     };
@@ -201,6 +257,7 @@ fn lexer() -> impl Parser<char, Vec<Spanned<Token>>, Error = Simple<char>> {
         ';' => Token::Semicolon,
         ',' => Token::Comma,
     }
+    .or(just("...").to(Token::Ellipsis))
     .or(text::newline().to(Token::Newline));
 
     let keywords_and_idents = ident().map(|v| match String::from_iter(v).as_str() {
@@ -220,7 +277,10 @@ fn lexer() -> impl Parser<char, Vec<Spanned<Token>>, Error = Simple<char>> {
         s => Token::Ident(s.into()),
     });
 
-    let comment = just("//").then(take_until(just('\n'))).to(Token::Comment);
+    // Note: the last line of a file might be a comment without a trailing newline
+    let comment = just("//")
+        .then(take_until(text::newline().or(end())))
+        .to(Token::Comment);
 
     let token = comment
         .or(binary)
@@ -260,9 +320,96 @@ fn parser() -> impl Parser<Token, Spanned<Expr>, Error = Simple<Token>> + Clone
             Token::ScientificNum(base, exp, neg_sign) => NumberLiteral::Scientific(base, exp, neg_sign),
         };
 
+        // An integer ratio like 3/4 or -2, only allowed as the exponent of a unit where it
+        // takes precedence over division, so 5 m^3/4 is 5 m^(3/4) rather than (5 m^3)/4. The
+        // ratio must be written without spaces, 5 m^3 / 4 is still a division.
+        let integer = select! {Token::DecimalNum(n) => n}
+            .try_map(|n, span| {
+                n.parse::<i32>()
+                    .map_err(|_| Simple::custom(span, "Expected an integer"))
+            })
+            .map_with_span(|n, span: Span| (n, span));
+
+        let denominator = just(Token::Div)
+            .map_with_span(|_, span: Span| span)
+            .then(integer)
+            .try_map(|(slash, (denominator, span)), _| {
+                if slash.end != span.start || denominator == 0 {
+                    return Err(Simple::custom(span, "Expected a ratio"));
+                }
+                Ok((slash, denominator))
+            });
+
+        let rational = just(Token::Sub)
+            .or_not()
+            .then(integer)
+            .then(denominator.or_not())
+            .try_map(|((sign, (numerator, span)), denominator), _| {
+                let numerator = if sign.is_some() {
+                    -numerator
+                } else {
+                    numerator
+                };
+                match denominator {
+                    None => Ok(Expr::Literal(Literal::Rational(numerator, 1))),
+                    Some((slash, denominator)) if slash.start == span.end => {
+                        Ok(Expr::Literal(Literal::Rational(numerator, denominator)))
+                    }
+                    Some((slash, _)) => Err(Simple::custom(slash, "Expected a ratio")),
+                }
+            })
+            .map_with_span(|expr, span| (expr, span));
+
+        // The exponent of a unit in a quantity literal, m^2, m^-1, m^3/4 or m^(3/4)
+        let unit_exponent = rational.clone().or(rational
+            .clone()
+            .delimited_by(just(Token::LParen), just(Token::RParen)));
+
+        // The exponent of any other value, 2^3 or 2^(3/4). A ratio needs parentheses here
+        // since 2^3/4 is (2^3)/4
+        let value_exponent = integer
+            .map(|(n, span)| (Expr::Literal(Literal::Rational(n, 1)), span))
+            .or(rational.delimited_by(just(Token::LParen), just(Token::RParen)));
+
+        // One of a unit, optionally raised to a power, 1 m or (1 m)^2
+        let unit_power = |unit: Spanned<String>, exp: Option<Spanned<Expr>>| {
+            let span = unit.1.clone();
+            let one_unit = (
+                Expr::Literal(Literal::Quantity(
+                    NumberLiteral::Decimal("1".to_string()),
+                    Some(unit),
+                )),
+                span.clone(),
+            );
+            match exp {
+                Some(exp) => {
+                    let span = span.start..exp.1.end;
+                    (
+                        Expr::BinOp(BinOp::Pow, Box::new(one_unit), Box::new(exp)),
+                        span,
+                    )
+                }
+                None => one_unit,
+            }
+        };
+
+        // A unit name with an optional exponent, m or m^2
+        let unit = ident
+            .map_with_span(|unit, span| (unit, span))
+            .then(just(Token::Pow).ignore_then(unit_exponent.clone()).or_not());
+
+        // 5 m or 5 m^2 where the exponent only applies to the unit, not the number
         let quantity = number
-            .then(ident.or_not())
-            .map(|(number, unit)| Expr::Literal(Literal::Quantity(number, unit)));
+            .map_with_span(|number, span: Span| (number, span))
+            .then(unit.clone().or_not())
+            .map(move |((number, number_span), unit)| match unit {
+                Some((unit, Some(exp))) => Expr::BinOp(
+                    BinOp::Mul,
+                    Box::new((Expr::Literal(Literal::Quantity(number, None)), number_span)),
+                    Box::new(unit_power(unit, Some(exp))),
+                ),
+                unit => Expr::Literal(Literal::Quantity(number, unit.map(|(unit, _)| unit))),
+            });
 
         let value = select! {
             Token::Nothing => Expr::Literal(Literal::Nothing),
@@ -277,10 +424,31 @@ fn parser() -> impl Parser<Token, Spanned<Expr>, Error = Simple<Token>> + Clone
             .separated_by(just(Token::Comma))
             .allow_trailing();
 
+        // x, y, rest...
+        // Only the last parameter is allowed to be a rest parameter
         let parameter_list = ident
             .clone()
+            .then(just(Token::Ellipsis).or_not())
             .separated_by(just(Token::Comma))
-            .allow_trailing();
+            .allow_trailing()
+            .try_map(|params, span| {
+                let mut parameters = Parameters::default();
+                for (name, ellipsis) in params {
+                    if parameters.rest.is_some() {
+                        return Err(Simple::custom(
+                            span,
+                            "A rest parameter must be the last parameter",
+                        ));
+                    }
+
+                    if ellipsis.is_some() {
+                        parameters.rest = Some(name);
+                    } else {
+                        parameters.names.push(name);
+                    }
+                }
+                Ok(parameters)
+            });
 
         // General named function assignment syntax
         // f(x) = 10 + x
@@ -321,33 +489,88 @@ fn parser() -> impl Parser<Token, Spanned<Expr>, Error = Simple<Token>> + Clone
         let var_declaration =
             assignment.map(|(name, value)| Expr::VarDeclaration(name, Box::new(value)));
 
-        // General syntax for unit declarations
-        let unit_decl = just(Token::Unit).ignore_then(ident).then(ident.or_not());
+        // General syntax for unit declarations, a unit can optionally be marked
+        // as the preferred unit when displaying quantities of the same dimension
+        // unit preferred newton N = ...
+        // Note: preferred is only a keyword here, so it can still be used as a name
+        let preferred = ident.try_map(|name, span| {
+            if name == "preferred" {
+                Ok(true)
+            } else {
+                Err(Simple::custom(span, "Expected preferred"))
+            }
+        });
+        let unit_decl = just(Token::Unit)
+            .ignore_then(
+                preferred
+                    .then(ident)
+                    .or(ident.map(|long_name| (false, long_name))),
+            )
+            .then(ident.or_not());
 
         // unit meter m
         let base_unit_decl = unit_decl
             .clone()
-            .map(|(long_name, short_name)| Expr::BaseUnitDecl(long_name, short_name));
+            .map(|((preferred, long_name), short_name)| {
+                Expr::BaseUnitDecl(long_name, short_name, preferred)
+            });
 
         // derived units also has a right hand side
         // unit mile mi = 1609.344 m
         let derived_unit_decl = unit_decl
             .then_ignore(just(Token::Assignment))
             .then(expr.clone())
-            .map(|((long_name, short_name), expr)| {
-                Expr::DerivedUnitDecl(long_name, short_name, Box::new(expr))
+            .map(|(((preferred, long_name), short_name), expr)| {
+                Expr::DerivedUnitDecl(long_name, short_name, Box::new(expr), preferred)
             });
 
         // prefix foo f = 42
-        let prefix_decl = just(Token::Prefix)
-            .ignore_then(ident)
+        // The long name can be left out with an underscore, prefix _ f = 42
+        let prefix = ident
             .then(ident.or_not())
             .then_ignore(just(Token::Assignment))
             .then(expr.clone())
-            .map(|((long_name, short_name), expr)| {
-                Expr::PrefixDecl(long_name, short_name, Box::new(expr))
+            .map_with_span(|((long_name, short_name), expr), span| {
+                let long_name = Some(long_name).filter(|name| name != "_");
+                (
+                    Expr::PrefixDecl(long_name, short_name, Box::new(expr)),
+                    span,
+                )
             });
 
+        // Several prefixes can be declared at once, separated by commas and optionally
+        // line breaks, prefix kilo k = 1000, mega M = 1e6
+        let prefix_decl = just(Token::Prefix)
+            .ignore_then(
+                prefix
+                    .separated_by(
+                        just(Token::Comma)
+                            .then(just(Token::Newline).or(just(Token::Comment)).repeated()),
+                    )
+                    .at_least(1),
+            )
+            .map(|mut prefixes| {
+                if prefixes.len() == 1 {
+                    prefixes.remove(0).0
+                } else {
+                    Expr::PrefixDecls(prefixes)
+                }
+            });
+
+        // assert 1 km == 1000 m
+        // Note: assert is not a keyword, so it is tried after the declarations
+        // in case something named assert is declared
+        let assert = ident
+            .try_map(|name, span| {
+                if name == "assert" {
+                    Ok(())
+                } else {
+                    Err(Simple::custom(span, "Expected assert"))
+                }
+            })
+            .ignore_then(expr.clone())
+            .map(|condition| Expr::Assert(Box::new(condition)));
+
         let atom = value
             .or(function_update)
             .or(function_decl)
@@ -356,6 +579,7 @@ fn parser() -> impl Parser<Token, Spanned<Expr>, Error = Simple<Token>> + Clone
             .or(derived_unit_decl)
             .or(base_unit_decl)
             .or(prefix_decl)
+            .or(assert)
             .or(ident.map(Expr::Variable))
             .map_with_span(|expr, span| (expr, span))
             // Expression surrounded with parentheses
@@ -383,13 +607,85 @@ fn parser() -> impl Parser<Token, Spanned<Expr>, Error = Simple<Token>> + Clone
                 (Expr::Call(Box::new(f), args.0), span)
             });
 
+        // x^2, x^-1, x^(3/4), x^-y or x^(any expression)
+        let exponent = just(Token::Sub)
+            .map_with_span(|_, span: Span| span)
+            .or_not()
+            .then(value_exponent.or(call.clone()));
+
+        // Negate an exponent if it has a minus sign in front of it
+        let negate = |minus: Option<Span>, (expr, span): Spanned<Expr>| match (minus, expr) {
+            (None, expr) => (expr, span),
+            (Some(minus), Expr::Literal(Literal::Rational(numerator, denominator))) => (
+                Expr::Literal(Literal::Rational(-numerator, denominator)),
+                minus.start..span.end,
+            ),
+            (Some(minus), expr) => (
+                Expr::UnaryOp(UnaryOp::Negate, Box::new((expr, span.clone()))),
+                minus.start..span.end,
+            ),
+        };
+
+        // Powers are right associative, 2^3^2 is 2^(3^2), and a minus sign in front of an
+        // exponent negates everything to the right of it, 2^-3^2 is 2^-(3^2)
+        // Note: boxing the parser keeps its type (and the stack frames used when parsing) small,
+        // without it the prelude can overflow the stack of test threads in debug builds
+        let power = call
+            .then(just(Token::Pow).ignore_then(exponent).repeated())
+            .map(move |(base, exponents)| {
+                let mut exponents = exponents.into_iter().rev();
+                let Some((minus, last)) = exponents.next() else {
+                    return base;
+                };
+
+                let mut exponent = negate(minus, last);
+                for (minus, operand) in exponents {
+                    let span = operand.1.start..exponent.1.end;
+                    let power = Expr::BinOp(BinOp::Pow, Box::new(operand), Box::new(exponent));
+                    exponent = negate(minus, (power, span));
+                }
+
+                let span = base.1.start..exponent.1.end;
+                (
+                    Expr::BinOp(BinOp::Pow, Box::new(base), Box::new(exponent)),
+                    span,
+                )
+            })
+            .boxed();
+
+        // A unit name can also follow other expressions than number literals, (2 + 3) m or x km^2
+        // is the expression multiplied by one of the unit. The rule is that any identifier
+        // directly after an operand is a unit, unless it is followed by "(" since that would
+        // be a separate function call.
+        let with_unit = power
+            .then(
+                unit.then(just(Token::LParen).rewind().or_not())
+                    .try_map(|(unit, paren), span| match paren {
+                        Some(_) => Err(Simple::custom(span, "Expected a unit")),
+                        None => Ok(unit),
+                    })
+                    .or_not(),
+            )
+            .map(move |(expr, unit)| match unit {
+                Some((unit, exp)) => {
+                    let unit = unit_power(unit, exp);
+                    let span = expr.1.start..unit.1.end;
+                    (
+                        Expr::BinOp(BinOp::Mul, Box::new(expr), Box::new(unit)),
+                        span,
+                    )
+                }
+                None => expr,
+            })
+            .boxed();
+
         let op = just(Token::Sub)
             .to(UnaryOp::Negate)
             .or(just(Token::Not).to(UnaryOp::Not));
 
         let unary =
             op.repeated()
-                .then(call.labelled("unary operand"))
+                .then(with_unit.labelled("unary operand"))
                 .foldr(|op, (expr, expr_span)| {
                     (
                         Expr::UnaryOp(op, Box::new((expr, expr_span.clone()))),
@@ -506,3 +802,247 @@ fn parser() -> impl Parser<Token, Spanned<Expr>, Error = Simple<Token>> + Clone
         .then_ignore(end())
         .map_with_span(|program, span| (Expr::Program(program), span))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn comment_at_end_of_file() {
+        assert!(parse("x = 1 // note").is_ok());
+        assert!(parse("x = 1\n// note").is_ok());
+        assert!(parse("x = 1\n// note\n").is_ok());
+        assert!(parse("// note").is_ok());
+    }
+
+    #[test]
+    fn line_continuation() {
+        let program = |source| match parse(source).unwrap() {
+            (Expr::Program(exprs), _) => {
+                exprs.into_iter().map(|(expr, _)| expr).collect::<Vec<_>>()
+            }
+            _ => panic!("Expected a program"),
+        };
+
+        let sum = program("1 +\n2");
+        assert!(matches!(sum.as_slice(), [Expr::BinOp(BinOp::Add, _, _)]));
+
+        let product = program("x = 2 *\n\n   3 // comment\ny = 1");
+        assert!(matches!(
+            product.as_slice(),
+            [Expr::VarDeclaration(_, rhs), Expr::VarDeclaration(_, _)]
+                if matches!(rhs.0, Expr::BinOp(BinOp::Mul, _, _))
+        ));
+
+        let comparison = program("1 + 2 < // comment\n4 and\ntrue");
+        assert!(matches!(
+            comparison.as_slice(),
+            [Expr::BinOp(BinOp::And, _, _)]
+        ));
+
+        // Without a trailing operator the line break still separates statements
+        assert_eq!(program("1\n-2").len(), 2);
+
+        // The REPL uses the same rule to know when to read another line
+        assert!(ends_with_operator("x = 2 *"));
+        assert!(ends_with_operator("1 + 2 < // comment\n"));
+        assert!(ends_with_operator("1 km in"));
+        assert!(!ends_with_operator("1 km"));
+        assert!(!ends_with_operator("x = 1 // a +"));
+        assert!(!ends_with_operator("sin(x)"));
+    }
+
+    /// Parse a single expression and throw away the spans
+    fn parse_expr(source: &str) -> Expr {
+        let (Expr::Program(mut exprs), _) = parse(source).unwrap() else {
+            panic!("Expected a program");
+        };
+        exprs.remove(0).0
+    }
+
+    #[test]
+    fn rational_exponent() {
+        let rational = |n, d| Expr::Literal(Literal::Rational(n, d));
+        let power = |expr| match expr {
+            Expr::BinOp(BinOp::Pow, base, exp) => (base.0, exp.0),
+            expr => panic!("Expected a power, found {expr:?}"),
+        };
+        let unit_exponent = |expr| match expr {
+            Expr::BinOp(BinOp::Mul, _, unit) => power(unit.0).1,
+            expr => panic!("Expected a quantity, found {expr:?}"),
+        };
+        let x = Expr::Variable("x".to_string());
+
+        assert_eq!(unit_exponent(parse_expr("5 m^3/4")), rational(3, 4));
+        assert_eq!(unit_exponent(parse_expr("5 m^(3/4)")), rational(3, 4));
+        assert_eq!(unit_exponent(parse_expr("5 m^2")), rational(2, 1));
+        assert_eq!(unit_exponent(parse_expr("5 m^-1/2")), rational(-1, 2));
+        assert_eq!(unit_exponent(parse_expr("x m^3/4")), rational(3, 4));
+
+        // Other values need parentheses around a ratio, x^3/4 is (x^3)/4
+        assert_eq!(power(parse_expr("x^(3/4)")), (x.clone(), rational(3, 4)));
+        assert_eq!(power(parse_expr("x^2")), (x.clone(), rational(2, 1)));
+        assert_eq!(power(parse_expr("x^-1")), (x.clone(), rational(-1, 1)));
+        assert!(matches!(
+            power(parse_expr("x^(3/4 + y)")),
+            (_, Expr::BinOp(BinOp::Add, _, _))
+        ));
+        assert!(matches!(parse_expr("x^2/2"), Expr::BinOp(BinOp::Div, _, _)));
+        assert!(matches!(
+            parse_expr("16^1/2"),
+            Expr::BinOp(BinOp::Div, _, _)
+        ));
+
+        // Spaces or a zero denominator turn the ratio back into a division
+        assert!(matches!(
+            parse_expr("5 m^3 / 4"),
+            Expr::BinOp(BinOp::Div, _, _)
+        ));
+        assert!(matches!(
+            parse_expr("5 m^3/0"),
+            Expr::BinOp(BinOp::Div, _, _)
+        ));
+    }
+
+    #[test]
+    fn division_is_not_rational() {
+        assert!(matches!(parse_expr("3/4"), Expr::BinOp(BinOp::Div, _, _)));
+        // The ratio only binds to the exponent, the rest is still an expression
+        assert!(matches!(
+            parse_expr("2 * m^3/4 / 5"),
+            Expr::BinOp(BinOp::Div, _, _)
+        ));
+    }
+
+    #[test]
+    fn unit_after_expression() {
+        let product = |expr| match expr {
+            Expr::BinOp(BinOp::Mul, a, b) => (a.0, b.0),
+            expr => panic!("Expected a product, found {expr:?}"),
+        };
+
+        let (sum, meter) = product(parse_expr("(2 + 3) meter"));
+        assert!(matches!(sum, Expr::BinOp(BinOp::Add, _, _)));
+        assert!(matches!(
+            meter,
+            Expr::Literal(Literal::Quantity(_, Some(_)))
+        ));
+
+        let (x, km) = product(parse_expr("x km^2"));
+        assert_eq!(x, Expr::Variable("x".to_string()));
+        assert!(matches!(km, Expr::BinOp(BinOp::Pow, _, _)));
+
+        assert!(matches!(
+            product(parse_expr("f(2) s")),
+            (Expr::Call(_, _), _)
+        ));
+        // A number literal followed by a unit is still a single quantity
+        assert!(matches!(
+            parse_expr("5 m"),
+            Expr::Literal(Literal::Quantity(_, Some(_)))
+        ));
+        // An identifier followed by parentheses is a call, not a unit
+        assert!(parse("x f(2)").is_err());
+    }
+
+    #[test]
+    fn multiple_prefixes() {
+        let Expr::PrefixDecls(prefixes) = parse_expr("prefix kilo k = 1000, mega M = 1e6") else {
+            panic!("Expected several prefix declarations");
+        };
+        let names: Vec<_> = prefixes
+            .iter()
+            .map(|(prefix, _)| match prefix {
+                Expr::PrefixDecl(long_name, short_name, _) => {
+                    (long_name.clone(), short_name.clone())
+                }
+                expr => panic!("Expected a prefix declaration, found {expr:?}"),
+            })
+            .collect();
+        assert_eq!(
+            names,
+            [
+                (Some("kilo".to_string()), Some("k".to_string())),
+                (Some("mega".to_string()), Some("M".to_string())),
+            ]
+        );
+
+        // A single prefix is still a plain declaration
+        assert!(matches!(
+            parse_expr("prefix kilo k = 1000"),
+            Expr::PrefixDecl(..)
+        ));
+        assert!(parse("prefix kilo k = 1000,").is_err());
+    }
+
+    #[test]
+    fn preferred_unit() {
+        assert!(matches!(
+            parse_expr("unit preferred newton N = 1000 g m / s^2"),
+            Expr::DerivedUnitDecl(long_name, _, _, true) if long_name == "newton"
+        ));
+        assert!(matches!(
+            parse_expr("unit meter m"),
+            Expr::BaseUnitDecl(long_name, _, false) if long_name == "meter"
+        ));
+
+        // preferred is only a keyword in a unit declaration
+        assert!(matches!(
+            parse_expr("unit preferred"),
+            Expr::BaseUnitDecl(long_name, None, false) if long_name == "preferred"
+        ));
+        assert!(matches!(
+            parse_expr("preferred = 3"),
+            Expr::VarDeclaration(name, _) if name == "preferred"
+        ));
+    }
+
+    #[test]
+    fn right_associative_power() {
+        let power = |expr| match expr {
+            Expr::BinOp(BinOp::Pow, base, exp) => (base.0, exp.0),
+            expr => panic!("Expected a power, found {expr:?}"),
+        };
+        let two = Expr::Literal(Literal::Quantity(
+            NumberLiteral::Decimal("2".to_string()),
+            None,
+        ));
+
+        let (base, exponent) = power(parse_expr("2^3^2"));
+        assert_eq!(base, two);
+        assert!(matches!(
+            power(exponent),
+            (
+                Expr::Literal(Literal::Rational(3, 1)),
+                Expr::Literal(Literal::Rational(2, 1))
+            )
+        ));
+
+        // A negative exponent applies to everything to the right of it
+        let (_, exponent) = power(parse_expr("2^-3^2"));
+        let Expr::UnaryOp(UnaryOp::Negate, exponent) = exponent else {
+            panic!("Expected a negated exponent, found {exponent:?}");
+        };
+        assert!(matches!(exponent.0, Expr::BinOp(BinOp::Pow, _, _)));
+
+        let (_, exponent) = power(parse_expr("2^-x"));
+        assert!(matches!(
+            exponent,
+            Expr::UnaryOp(UnaryOp::Negate, x) if x.0 == Expr::Variable("x".to_string())
+        ));
+    }
+
+    #[test]
+    fn assert_is_not_a_keyword() {
+        assert!(matches!(parse_expr("assert x > 2"), Expr::Assert(_)));
+        assert!(matches!(
+            parse_expr("assert = 3"),
+            Expr::VarDeclaration(name, _) if name == "assert"
+        ));
+        assert!(matches!(
+            parse_expr("assert(x) = x > 2"),
+            Expr::FunctionDecl(name, _, _) if name == "assert"
+        ));
+        assert_eq!(parse_expr("assert"), Expr::Variable("assert".to_string()));
+    }
+}
@@ -8,6 +8,7 @@ import styled from "styled-components";
 import {
   addCell,
   Cell as CellType,
+  MAX_CELLS,
   recoverSession,
   reducer,
   removeCell,
@@ -54,9 +55,15 @@ const Container = styled.div`
 const Actions = styled.div`
   display: flex;
   justify-content: flex-end;
+  align-items: center;
+  gap: 1rem;
   width: 100%;
 `;
 
+const LimitMessage = styled.span`
+  opacity: 0.7;
+`;
+
 function App() {
   const [state, dispatch] = useReducer(
     reducer,
@@ -66,6 +73,7 @@ function App() {
       loaded: false,
       sessionRestored: false,
       helpOpen: false,
+      cellLimitReached: false,
     },
     (state: State) => {
       // Load the previous session from local
@@ -136,6 +144,11 @@ function App() {
                 />
               ))}
               <Actions>
+                {state.cellLimitReached && (
+                  <LimitMessage>
+                    A notebook can have at most {MAX_CELLS} cells
+                  </LimitMessage>
+                )}
                 <Button
                   onClick={() => addCell(state, dispatch)}
                   title="Add cell"
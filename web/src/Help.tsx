@@ -106,6 +106,14 @@ update height = 35 m
               
 area = width * height`}
             />
+            A unit name can follow a variable or an expression in parentheses as well as a
+            number, which multiplies the value by one of that unit. A name followed by
+            parentheses is always a function call though, never a unit.
+            <Example
+              value={`(2 + 3) meter
+side = 4
+side km^2`}
+            />
 
             <h2>Literals</h2>
             Binary, hexadecimal and scientific literals are supported
@@ -164,6 +172,16 @@ unit dollar usd = 10 kr
             <Example
               value={`prefix super = 10000000
 15 supergram`}
+            />
+            A short name can be given after the long name. Use <code>_</code> instead of the long name to declare a prefix that only has a short name.
+            <Example
+              value={`prefix _ Q = 1000000000000000000000000000000
+2 Qg`}
+            />
+            Several prefixes can be declared at once by separating them with commas.
+            <Example
+              value={`prefix mega M = 1e6, giga G = 1e9
+3 Gm`}
             />
             <h1>Language reference</h1>
             Work in progress. Will write this once more of the language is completed.
@@ -176,6 +194,7 @@ unit dollar usd = 10 kr
               <li><code>in</code></li>
               <li><code>unit</code></li>
               <li><code>prefix</code></li>
+              <li><code>assert</code> (check that a condition is true)</li>
               <li><code>=</code> (assignment)</li>
             </ul>
             <h3>Arithmetic operators</h3>
@@ -184,6 +203,7 @@ unit dollar usd = 10 kr
               <li><code>-</code> (subtract or negate)</li>
               <li><code>/</code> (divide)</li>
               <li><code>*</code> (multiply)</li>
+              <li><code>^</code> (power, <code>5 m^3/4</code> is <code>5 m^(3/4)</code> but <code>x^3/4</code> is <code>(x^3)/4</code>, and <code>2^3^2</code> is <code>2^(3^2)</code>)</li>
             </ul>
 
             <h3>Relational operators</h3>
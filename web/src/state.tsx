@@ -1,14 +1,19 @@
 import init, {
   clear_state,
+  compact_state,
   insert_cell,
   read_cell_code,
   read_cell_output,
   read_cell_time,
   remove_cell,
+  set_max_cells,
   write_cell,
 } from "web_bindings";
 import { useEffect } from "react";
 
+// The largest number of cells a notebook can have
+export const MAX_CELLS = 1000;
+
 export type Cell = {
   code: string;
   output: string;
@@ -21,6 +26,7 @@ export type State = {
   previousSession: string[] | null;
   sessionRestored: boolean;
   loaded: boolean;
+  cellLimitReached: boolean;
 };
 
 export type Action =
@@ -35,12 +41,16 @@ export type Action =
   | {
     type: "add cell";
   }
+  | {
+    type: "cell limit reached";
+  }
   | {
     type: "remove cell";
     index: number;
   }
   | {
     type: "restore session";
+    restoredCells: number;
   }
   | {
     type: "toggle help";
@@ -76,10 +86,15 @@ export const reducer = (state: State, action: Action): State => {
       return { ...state, cells };
     }
 
+    case "cell limit reached": {
+      return { ...state, cellLimitReached: true };
+    }
+
     case "remove cell": {
       return {
         ...state,
         cells: state.cells.filter((_c, i) => i !== action.index),
+        cellLimitReached: false,
       };
     }
 
@@ -89,11 +104,14 @@ export const reducer = (state: State, action: Action): State => {
       return {
         ...state,
         sessionRestored: true,
-        cells: state.previousSession.map((code, i) => ({
-          code: read_cell_code(i),
-          output: read_cell_output(i),
-          time: read_cell_time(i),
-        })),
+        cellLimitReached: action.restoredCells < state.previousSession.length,
+        cells: state.previousSession
+          .slice(0, action.restoredCells)
+          .map((_, i) => ({
+            code: read_cell_code(i),
+            output: read_cell_output(i),
+            time: read_cell_time(i),
+          })),
       };
     }
 
@@ -116,6 +134,7 @@ export function writeCell(
   dispatch: (action: Action) => void,
 ) {
   const updatedCells = write_cell(index, code);
+  compact_state();
   dispatch({ type: "write", updatedCells, writtenCell: index });
 }
 
@@ -123,7 +142,10 @@ export function addCell(
   state: State,
   dispatch: (action: Action) => void,
 ) {
-  insert_cell(state.cells.length);
+  if (!insert_cell(state.cells.length)) {
+    dispatch({ type: "cell limit reached" });
+    return;
+  }
   dispatch({ type: "add cell" });
 }
 
@@ -139,17 +161,21 @@ export function recoverSession(
   clear_state();
   if (state.previousSession === null) return;
 
-  state.previousSession.forEach((code, index) => {
-    insert_cell(index);
-    write_cell(index, code);
-  });
+  // Stop restoring once the notebook has the maximum number of cells
+  let restoredCells = 0;
+  for (const code of state.previousSession) {
+    if (!insert_cell(restoredCells)) break;
+    write_cell(restoredCells, code);
+    restoredCells++;
+  }
 
-  dispatch({ type: "restore session" });
+  dispatch({ type: "restore session", restoredCells });
 }
 
 export function useWasm(dispatch: (action: Action) => void) {
   useEffect(() => {
     init().then(() => {
+      set_max_cells(MAX_CELLS);
       insert_cell(0);
       dispatch({ type: "loaded wasm" });
     });
@@ -1,6 +1,6 @@
 use hypatia_lib::{
-    format_unit,
-    number::Number,
+    format_engineering, format_unit,
+    number::{Number, NumberStyle},
     units::Quantity,
     Environment, Value,
 };
@@ -12,7 +12,13 @@ pub struct Format {
 }
 
 pub fn get_formats(value: &Value, env: &Environment) -> Vec<Format> {
-    [exact, approx, debug]
+    // Cells ending with a declaration evaluate to nothing,
+    // leave their output empty rather than cluttering the notebook
+    if value.is_nothing() {
+        return Vec::new();
+    }
+
+    [exact, approx, engineering, debug]
         .iter()
         .filter_map(|f| f(value, env))
         .collect()
@@ -30,9 +36,12 @@ fn exact(value: &Value, env: &Environment) -> Option<Format> {
                 None
             }
         }
-        Value::Nothing => Some(format!("Nothing")),
+        // Nothing has no formats at all, see get_formats
+        Value::Nothing => None,
         Value::Bool(b) => Some(format!("{b}")),
-        Value::Function(_) => Some(format!("Function")),
+        Value::String(s) => Some(s.clone()),
+        Value::Function(_) | Value::NativeFunction(_) => Some(format!("Function")),
+        Value::List(_) => Some(format!("{value}")),
     };
 
     html.map(|html| Format {
@@ -49,13 +58,57 @@ fn approx(value: &Value, env: &Environment) -> Option<Format> {
 
     Some(Format {
         name: "Approx".to_string(),
-        repr: format!("Approx. {} {long_unit_str}", number.into_approx()),
+        repr: format!(
+            "Approx. {} {long_unit_str}",
+            number.format(NumberStyle::Approx)
+        ),
+    })
+}
+
+fn engineering(value: &Value, env: &Environment) -> Option<Format> {
+    let Value::Quantity(q) = value else {
+        return None;
+    };
+
+    Some(Format {
+        name: "Engineering".to_string(),
+        repr: format_engineering(q.clone(), env),
     })
 }
 
 fn debug(value: &Value, _: &Environment) -> Option<Format> {
     Some(Format {
-        repr: format!("{value:#?}"),
+        repr: describe(value, 0),
         name: "Debug".to_string(),
     })
 }
+
+/// Describe the structure of a value (its type and, for quantities, how the number is
+/// stored, the scale of the unit and its dimension) without showing any Rust internals
+fn describe(value: &Value, depth: usize) -> String {
+    let indent = "  ".repeat(depth + 1);
+    match value {
+        Value::Quantity(Quantity { number, unit }) => {
+            let form = match number {
+                Number::Exact(_) => "exact",
+                Number::Approx(_) => "approximate",
+            };
+            format!(
+                "quantity\n{indent}number: {number} ({form})\n{indent}scale: {}\n{indent}dimension: {}",
+                unit.0,
+                unit.dimension()
+            )
+        }
+        Value::List(values) => {
+            let mut repr = format!("list of {} values", values.len());
+            for (i, value) in values.iter().enumerate() {
+                repr.push_str(&format!("\n{indent}{i}: {}", describe(value, depth + 1)));
+            }
+            repr
+        }
+        Value::String(s) => format!("string of {} characters: {s:?}", s.chars().count()),
+        Value::Bool(b) => format!("bool: {b}"),
+        Value::Nothing | Value::Function(_) => value.kind().to_string(),
+        Value::NativeFunction(function) => format!("native function: {}", function.name()),
+    }
+}
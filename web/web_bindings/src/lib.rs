@@ -19,7 +19,9 @@ cfg_if! {
 
 #[derive(Debug, Clone)]
 struct Cell {
-    environment: Environment,
+    // The environment produced by running the cell. It is dropped by `compact_state`
+    // for cells far away from the last edit and recomputed when it is needed again.
+    environment: Option<Environment>,
     source_code: String,
     runtime: Option<Duration>,
     output: Result<Vec<Format>, Vec<Error>>,
@@ -31,20 +33,69 @@ lazy_static! {
 
 static STATE: Mutex<Vec<Cell>> = Mutex::new(Vec::new());
 
+/// The maximum number of cells in the notebook, see `set_max_cells`
+static MAX_CELLS: Mutex<usize> = Mutex::new(1000);
+
+/// The index of the cell that was written to most recently
+static LAST_EDITED: Mutex<usize> = Mutex::new(0);
+
+/// How many cells before and after the last edited cell that
+/// keep their environment when the state is compacted
+const KEPT_ENVIRONMENTS: usize = 10;
+
+/// Get a copy of the environment produced by the cell before the given one (or a empty env for the first cell).
+/// Earlier cells whose environments have been dropped are re-run from the closest cell that still has one.
+fn environment_before(cell_index: usize, cells: &mut [Cell]) -> Environment {
+    let first_missing = (0..cell_index)
+        .rev()
+        .find(|&i| cells[i].environment.is_some())
+        .map_or(0, |i| i + 1);
+
+    for i in first_missing..cell_index {
+        refresh(i, cells);
+    }
+
+    // Each cell gets its own snapshot, since clones of an environment share their state
+    match cell_index.checked_sub(1) {
+        Some(previous) => cells[previous]
+            .environment
+            .as_ref()
+            .expect("The previous cell has been refreshed")
+            .snapshot(),
+        None => EMPTY_ENV.snapshot(),
+    }
+}
+
 /// Re-run the code for a cell
-fn refresh(cell_index: usize, cells: &mut Vec<Cell>) {
-    let mut env = if cell_index == 0 {
-        EMPTY_ENV.clone()
-    } else {
-        cells[cell_index - 1].environment.clone()
-    };
+fn refresh(cell_index: usize, cells: &mut [Cell]) {
+    let mut env = environment_before(cell_index, cells);
 
     let cell = &mut cells[cell_index];
 
     let (output, runtime) = run(&cell.source_code, &mut env);
     cell.output = output;
     cell.runtime = Some(runtime);
-    cell.environment = env;
+    cell.environment = Some(env);
+}
+
+/// Limit the number of cells in the notebook
+#[wasm_bindgen]
+pub fn set_max_cells(max_cells: usize) {
+    *MAX_CELLS.lock().unwrap() = max_cells;
+}
+
+/// Drop the environments of cells far away from the last edited cell to bound the
+/// memory used by large notebooks. They are recomputed if they are needed again.
+#[wasm_bindgen]
+pub fn compact_state() {
+    let last_edited = *LAST_EDITED.lock().unwrap();
+    let mut cells = STATE.lock().unwrap();
+
+    for (i, cell) in cells.iter_mut().enumerate() {
+        if i.abs_diff(last_edited) > KEPT_ENVIRONMENTS {
+            cell.environment = None;
+        }
+    }
 }
 
 #[wasm_bindgen]
@@ -57,13 +108,10 @@ pub fn clear_state() {
 pub fn write_cell(cell_index: usize, code: &str) -> Vec<usize> {
     utils::set_panic_hook();
     let mut cells = STATE.lock().unwrap();
+    *LAST_EDITED.lock().unwrap() = cell_index;
 
     // Get the environment produced by the previous cell or use a empty env if this is the first one
-    let mut env = if cell_index == 0 {
-        EMPTY_ENV.clone()
-    } else {
-        cells.get(cell_index - 1).unwrap().environment.clone()
-    };
+    let mut env = environment_before(cell_index, &mut cells);
 
     let cell = cells.get_mut(cell_index).expect("Invalid cell index");
 
@@ -73,7 +121,7 @@ pub fn write_cell(cell_index: usize, code: &str) -> Vec<usize> {
         source_code: code.to_string(),
         output,
         runtime: Some(runtime),
-        environment: env,
+        environment: Some(env),
     };
 
     // "Refresh" all of the cells dependent on the one that has changed
@@ -92,19 +140,25 @@ pub fn write_cell(cell_index: usize, code: &str) -> Vec<usize> {
     refreshed_cells
 }
 
+/// Insert a new empty cell, returns false if the notebook already has the maximum number of cells
 #[wasm_bindgen]
-pub fn insert_cell(cell_index: usize) {
+pub fn insert_cell(cell_index: usize) -> bool {
     let mut cells = STATE.lock().unwrap();
-    // Fixme: don't like this dummy state
+    if cells.len() >= *MAX_CELLS.lock().unwrap() {
+        return false;
+    }
+
+    // The environment will be computed once it is needed
     cells.insert(
         cell_index,
         Cell {
-            environment: Environment::new(),
+            environment: None,
             source_code: String::new(),
             output: Ok(Vec::new()),
             runtime: None,
         },
     );
+    true
 }
 
 #[wasm_bindgen]